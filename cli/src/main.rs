@@ -0,0 +1,462 @@
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::process::ExitCode;
+
+use nalgebra::Vector2;
+
+use brachistochrone_solver::{
+    Body, Brachistochrone, BrachistochroneError, Grid, Multigrid, PathSample, Physics, Refine,
+    RefinedPath, Solver, Stencil, SvgOptions,
+};
+
+const USAGE: &str = "\
+Usage: brachistochrone-cli --start X,Y --end X,Y [OPTIONS]
+
+Solves the Brachistochrone problem between two points (in metres) and prints the nodes of the
+path, along with the time elapsed, the speed and the slope when reaching each of them.
+
+Options:
+    --start X,Y             Start point
+    --end X,Y               End point
+    --resolution N          Cells along the longest side of the rectangle between both
+                            points [default: 50]
+    --friction MU           Coulomb friction coefficient [default: 0]
+    --initial-speed V       Speed at the start point, in m/s [default: 0]
+    --drag C                Quadratic drag coefficient per unit mass, in 1/m [default: 0]
+    --body BODY             point-mass, solid-sphere, hollow-sphere, solid-disk or ring
+                            [default: point-mass]
+    --stencil STENCIL       square:R or radius:R, moves of up to R cells [default: square:8]
+    --coprime               Drop moves that are multiples of shorter ones
+    --backward              Also allow moves going backwards in x, which with friction makes
+                            every solver work as forward
+    --solver SOLVER         dp, rolling, dijkstra, multigrid, forward or from-start, which is
+                            forward going on to every node [default: dp]
+    --refine                Move the nodes of the path off the grid to make it faster, see below
+    --format FORMAT         csv or json [default: csv]
+    --output FILE           Write the path to FILE instead of the standard output
+    --svg FILE              Also draw the path, as an SVG figure written to FILE
+    --cycloid               Draw the cycloid through both points in the figure
+    --straight-line         Draw the straight line between both points in the figure
+    --values FILE           Also write the least time to the end point from every node, along
+                            with the move to make there, to FILE: a heatmap if it ends in
+                            .png, a JSON matrix if it ends in .json, and CSV otherwise
+    -h, --help              Print this message
+
+The CSV output has a header and one `x,y,time,speed,angle` row per node, the angle being that
+of the segment ending at the node, in radians. The total time and length of the path are printed
+to the standard error. The JSON output is an object with the total time and length, and the
+nodes.
+
+With --refine, the nodes are still those on the grid, and the time along the refined path is
+printed to the standard error, or added to the JSON output as `refined_time` along with its
+nodes as `refined_path`. The figure then shows the refined path.
+
+Exit codes:
+    0    Success
+    1    The output, the figure or the values couldn't be written
+    2    Invalid arguments
+    3    Invalid problem: an endpoint is out of the grid, or higher than the body can get
+    4    The end point can't be reached
+    5    Not enough memory for the solver
+    130  The solve was cancelled";
+
+/// Size of the value heatmap, in pixels along its longest side, unless the grid is even larger
+const HEATMAP_SIZE: u32 = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Csv,
+    Json,
+}
+
+struct Args {
+    start: Vector2<f32>,
+    end: Vector2<f32>,
+    resolution: usize,
+    physics: Physics,
+    stencil: Stencil,
+    solver: Solver,
+    refine: bool,
+    format: Format,
+    output: Option<String>,
+    svg: Option<String>,
+    svg_options: SvgOptions,
+    values: Option<String>,
+}
+
+enum Command {
+    Solve(Args),
+    Help,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut start = None;
+    let mut end = None;
+    let mut resolution = 50;
+    let mut physics = Physics::default();
+    let mut stencil = Stencil::default();
+    let mut coprime = false;
+    let mut backward = false;
+    let mut solver = Solver::Dp;
+    let mut refine = false;
+    let mut format = Format::Csv;
+    let mut output = None;
+    let mut svg = None;
+    let mut svg_options = SvgOptions::default();
+    let mut values = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--start" => start = Some(parse_point(&value()?)?),
+            "--end" => end = Some(parse_point(&value()?)?),
+            "--resolution" => resolution = parse_number(&arg, &value()?)?,
+            "--friction" => physics.friction = parse_finite(&arg, &value()?)?,
+            "--initial-speed" => physics.initial_speed = parse_finite(&arg, &value()?)?,
+            "--drag" => physics.drag = parse_finite(&arg, &value()?)?,
+            "--body" => {
+                physics.body = match value()?.as_str() {
+                    "point-mass" => Body::PointMass,
+                    "solid-sphere" => Body::SolidSphere,
+                    "hollow-sphere" => Body::HollowSphere,
+                    "solid-disk" => Body::SolidDisk,
+                    "ring" => Body::Ring,
+                    other => return Err(format!("unknown body: {other}")),
+                }
+            }
+            "--stencil" => stencil = parse_stencil(&value()?)?,
+            "--coprime" => coprime = true,
+            "--backward" => backward = true,
+            "--solver" => {
+                solver = match value()?.as_str() {
+                    "dp" => Solver::Dp,
+                    "rolling" => Solver::Rolling,
+                    "dijkstra" => Solver::Dijkstra,
+                    "multigrid" => Solver::Multigrid(Multigrid::default()),
+                    "forward" => Solver::Forward,
+                    "from-start" => Solver::FromStart,
+                    other => return Err(format!("unknown solver: {other}")),
+                }
+            }
+            "--refine" => refine = true,
+            "--format" => {
+                format = match value()?.as_str() {
+                    "csv" => Format::Csv,
+                    "json" => Format::Json,
+                    other => return Err(format!("unknown format: {other}")),
+                }
+            }
+            "--output" => output = Some(value()?),
+            "--svg" => svg = Some(value()?),
+            "--cycloid" => svg_options.cycloid = true,
+            "--straight-line" => svg_options.straight_line = true,
+            "--values" => values = Some(value()?),
+            other => return Err(format!("unknown argument: {other}")),
+        }
+    }
+
+    if physics.friction < 0. || physics.initial_speed < 0. || physics.drag < 0. {
+        return Err("friction, initial speed and drag can't be negative".into());
+    }
+
+    if resolution == 0 {
+        return Err("the resolution must be positive".into());
+    }
+
+    if coprime {
+        stencil = stencil.coprime();
+    }
+
+    if backward {
+        stencil = stencil.with_backward();
+    }
+
+    let start = start.ok_or("missing --start")?;
+    let end = end.ok_or("missing --end")?;
+
+    if start == end {
+        return Err("the start and end points must be different".into());
+    }
+
+    Ok(Command::Solve(Args {
+        start,
+        end,
+        resolution,
+        physics,
+        stencil,
+        solver,
+        refine,
+        format,
+        output,
+        svg,
+        svg_options,
+        values,
+    }))
+}
+
+fn parse_number<T: std::str::FromStr>(arg: &str, s: &str) -> Result<T, String> {
+    s.trim()
+        .parse()
+        .map_err(|_| format!("invalid value for {arg}: {s}"))
+}
+
+/// Same as `parse_number`, for numbers that also have to be finite
+fn parse_finite(arg: &str, s: &str) -> Result<f32, String> {
+    let x: f32 = parse_number(arg, s)?;
+
+    match x.is_finite() {
+        true => Ok(x),
+        false => Err(format!("invalid value for {arg}: {s}")),
+    }
+}
+
+fn parse_point(s: &str) -> Result<Vector2<f32>, String> {
+    let (x, y) = s
+        .split_once(',')
+        .ok_or(format!("invalid point: {s}, expected X,Y"))?;
+
+    let x: f32 = parse_number("x", x)?;
+    let y: f32 = parse_number("y", y)?;
+
+    match x.is_finite() && y.is_finite() {
+        true => Ok(Vector2::new(x, y)),
+        false => Err(format!("invalid point: {s}")),
+    }
+}
+
+fn parse_stencil(s: &str) -> Result<Stencil, String> {
+    let (kind, r) = s.split_once(':').ok_or(format!(
+        "invalid stencil: {s}, expected square:R or radius:R"
+    ))?;
+
+    let r: i32 = parse_number("the stencil radius", r)?;
+
+    if r < 1 {
+        return Err("the stencil radius must be positive".into());
+    }
+
+    match kind {
+        "square" => Ok(Stencil::square(r)),
+        "radius" => Ok(Stencil::radius(r)),
+        _ => Err(format!("unknown stencil: {kind}")),
+    }
+}
+
+fn solve(args: &Args) -> Result<Brachistochrone, BrachistochroneError> {
+    // Leave room for the path to go below the lowest endpoint, down to the ground, and above the
+    // start point as far as the body can get
+    let top = args.start.y + args.physics.max_rise();
+    let grid = Grid::fitted(args.start, args.end, args.resolution, 0., top)?;
+
+    let mut brac = Brachistochrone::new(
+        grid,
+        args.start,
+        args.end,
+        args.stencil.clone(),
+        args.physics,
+    )?;
+
+    brac.solve_with(args.solver)?;
+
+    Ok(brac)
+}
+
+fn write_csv(w: &mut impl Write, samples: &[PathSample]) -> io::Result<()> {
+    writeln!(w, "x,y,time,speed,angle")?;
+
+    for s in samples {
+        writeln!(
+            w,
+            "{},{},{},{},{}",
+            s.position.x, s.position.y, s.elapsed, s.speed, s.angle
+        )?;
+    }
+
+    Ok(())
+}
+
+fn write_json(w: &mut impl Write, path: &Solution) -> io::Result<()> {
+    let mut json = format!(
+        "{{\n  \"total_time\": {},\n  \"arc_length\": {},\n  \"nodes\": [",
+        path.total, path.length
+    );
+
+    for (i, s) in path.samples.iter().enumerate() {
+        let sep = if i == 0 { "" } else { "," };
+        let _ = write!(
+            json,
+            "{sep}\n    {{ \"x\": {}, \"y\": {}, \"time\": {}, \"speed\": {}, \"angle\": {} }}",
+            s.position.x, s.position.y, s.elapsed, s.speed, s.angle
+        );
+    }
+
+    json.push_str("\n  ]");
+
+    if let Some(refined) = path.refined {
+        let points: Vec<_> = refined
+            .points
+            .iter()
+            .map(|p| format!("[{}, {}]", p.x, p.y))
+            .collect();
+
+        let _ = write!(
+            json,
+            ",\n  \"refined_time\": {},\n  \"refined_path\": [{}]",
+            refined.time,
+            points.join(", ")
+        );
+    }
+
+    json.push_str("\n}");
+
+    writeln!(w, "{json}")
+}
+
+fn exit_code(err: &BrachistochroneError) -> u8 {
+    match err {
+        BrachistochroneError::OutOfBounds(_)
+        | BrachistochroneError::EndAboveStart
+        | BrachistochroneError::SameEndpoints => 3,
+        BrachistochroneError::Unreachable => 4,
+        BrachistochroneError::AllocationTooLarge { .. } => 5,
+        // Same as being interrupted by SIGINT
+        BrachistochroneError::Cancelled => 130,
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Command::Solve(args)) => args,
+        Ok(Command::Help) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let brac = match solve(&args) {
+        Ok(brac) => brac,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::from(exit_code(&err));
+        }
+    };
+
+    if brac.snap_error() > 1e-4 {
+        eprintln!(
+            "warning: endpoints are off the grid, snapped by up to {:.4} m",
+            brac.snap_error()
+        );
+    }
+
+    let samples = brac.path_samples();
+    let total = brac.total_time().expect("the path was solved");
+    let length = brac.arc_length();
+    let refined = args
+        .refine
+        .then(|| brac.refine(Refine::default()))
+        .flatten();
+
+    if args.refine && refined.is_none() {
+        eprintln!("warning: the path couldn't be refined");
+    }
+
+    let path = Solution {
+        total,
+        length,
+        samples: &samples,
+        refined: refined.as_ref(),
+    };
+
+    let result = match &args.output {
+        Some(file) => File::create(file).and_then(|f| write_path(&args, BufWriter::new(f), &path)),
+        None => write_path(&args, io::stdout().lock(), &path),
+    };
+
+    if let Err(err) = result {
+        eprintln!("error: failed to write the path: {err}");
+        return ExitCode::from(1);
+    }
+
+    if let Some(file) = &args.svg {
+        let (points, label) = match &refined {
+            Some(refined) => (refined.points.clone(), "Refined path"),
+            None => (brac.world_path(), args.svg_options.label),
+        };
+        let figure = brac.to_svg(
+            &points,
+            &SvgOptions {
+                label,
+                ..args.svg_options
+            },
+        );
+
+        if let Err(err) = std::fs::write(file, figure) {
+            eprintln!("error: failed to write the figure: {err}");
+            return ExitCode::from(1);
+        }
+    }
+
+    if let Some(file) = &args.values
+        && let Err(err) = File::create(file).and_then(|f| write_values(&brac, file, f))
+    {
+        eprintln!("error: failed to write the values: {err}");
+        return ExitCode::from(1);
+    }
+
+    if args.format == Format::Csv {
+        eprintln!("total time: {total} s");
+        eprintln!("arc length: {length} m");
+
+        if let Some(refined) = &refined {
+            eprintln!(
+                "refined time: {} s ({} s faster, {} iterations)",
+                refined.time,
+                refined.improvement(),
+                refined.iterations
+            );
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Everything written to the output
+struct Solution<'a> {
+    total: f32,
+    length: f32,
+    samples: &'a [PathSample],
+    refined: Option<&'a RefinedPath>,
+}
+
+fn write_path(args: &Args, mut w: impl Write, path: &Solution) -> io::Result<()> {
+    match args.format {
+        Format::Csv => write_csv(&mut w, path.samples)?,
+        Format::Json => write_json(&mut w, path)?,
+    }
+
+    w.flush()
+}
+
+/// Writes the value field of the solved problem, in the format given by the extension of `file`
+fn write_values(brac: &Brachistochrone, file: &str, f: File) -> io::Result<()> {
+    let field = brac.value_field();
+    let w = BufWriter::new(f);
+
+    match file.rsplit_once('.').map(|(_, ext)| ext) {
+        Some("png") => {
+            let n = field.grid.nx.max(field.grid.ny) as u32 + 1;
+
+            // Make the image at least a few hundred pixels wide
+            field.write_png(w, (HEATMAP_SIZE / n).max(1))
+        }
+        Some("json") => field.write_json(w),
+        _ => field.write_csv(w),
+    }
+}
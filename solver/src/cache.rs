@@ -0,0 +1,417 @@
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hasher;
+use std::io::{self, Read, Write};
+
+use nalgebra::Vector2;
+
+use super::{Body, Brachistochrone, Refine, Solver};
+
+/// Identifies cache files, followed by the format version
+const MAGIC: &[u8; 4] = b"BRCH";
+const VERSION: u32 = 2;
+
+/// Number of paths a [`PathCache`] holds by default
+const DEFAULT_CAPACITY: usize = 256;
+
+/// Solved path, as stored in a [`PathCache`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedPath {
+    /// Time predicted by the solver
+    pub time: f32,
+    /// Nodes of the path, in world coordinates
+    pub points: Vec<Vector2<f32>>,
+}
+
+/// Solved paths, keyed by [`Brachistochrone::cache_key`], so solving the same problem again
+/// takes no time. Once full, the oldest paths make room for new ones. Can be saved to and
+/// loaded from a compact binary file.
+#[derive(Debug)]
+pub struct PathCache {
+    entries: HashMap<u64, CachedPath>,
+    // Keys from the oldest to the newest
+    order: VecDeque<u64>,
+    capacity: usize,
+}
+
+impl Default for PathCache {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+impl PathCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cache holding at most `capacity` paths
+    pub fn with_capacity(capacity: usize) -> Self {
+        PathCache {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn get(&self, key: u64) -> Option<&CachedPath> {
+        self.entries.get(&key)
+    }
+
+    pub fn insert(&mut self, key: u64, path: CachedPath) {
+        if self.entries.insert(key, path).is_some() {
+            return;
+        }
+
+        self.order.push_back(key);
+
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Reads a cache written by [`PathCache::write_to`]
+    pub fn read_from(mut r: impl Read) -> io::Result<PathCache> {
+        let mut magic = [0; 4];
+        r.read_exact(&mut magic)?;
+
+        if &magic != MAGIC || read_u32(&mut r)? != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a path cache, or written by another version",
+            ));
+        }
+
+        let mut cache = PathCache::new();
+
+        for _ in 0..read_u64(&mut r)? {
+            let key = read_u64(&mut r)?;
+            let time = f32::from_bits(read_u32(&mut r)?);
+            let len = read_u64(&mut r)?;
+
+            let points = (0..len)
+                .map(|_| {
+                    let x = f32::from_bits(read_u32(&mut r)?);
+                    let y = f32::from_bits(read_u32(&mut r)?);
+
+                    Ok(Vector2::new(x, y))
+                })
+                .collect::<io::Result<_>>()?;
+
+            cache.insert(key, CachedPath { time, points });
+        }
+
+        Ok(cache)
+    }
+
+    /// Writes every entry from the oldest to the newest, with all numbers in little endian
+    pub fn write_to(&self, mut w: impl Write) -> io::Result<()> {
+        w.write_all(MAGIC)?;
+        w.write_all(&VERSION.to_le_bytes())?;
+        w.write_all(&(self.entries.len() as u64).to_le_bytes())?;
+
+        for key in &self.order {
+            let path = &self.entries[key];
+
+            w.write_all(&key.to_le_bytes())?;
+            w.write_all(&path.time.to_bits().to_le_bytes())?;
+            w.write_all(&(path.points.len() as u64).to_le_bytes())?;
+
+            for p in &path.points {
+                w.write_all(&p.x.to_bits().to_le_bytes())?;
+                w.write_all(&p.y.to_bits().to_le_bytes())?;
+            }
+        }
+
+        w.flush()
+    }
+}
+
+fn read_u32(r: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    r.read_exact(&mut buf)?;
+
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(r: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    r.read_exact(&mut buf)?;
+
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// 64-bit FNV-1a. Unlike the hasher in `std`, its output is guaranteed to stay the same, which
+/// matters for keys saved to disk.
+struct Fnv(u64);
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 = (self.0 ^ *b as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+
+    // The default ones hash the bytes in native order, so keys would depend on the machine
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+}
+
+fn write_vec(h: &mut impl Hasher, v: Vector2<f32>) {
+    h.write_u32(v.x.to_bits());
+    h.write_u32(v.y.to_bits());
+}
+
+impl Brachistochrone {
+    /// Hash of everything the solution found by `solver` depends on: the grid, both endpoints,
+    /// the stencil, the physical parameters, the obstacles and the solver itself, along with the
+    /// options of [`Brachistochrone::refine`] if the path to be stored is the refined one. `None`
+    /// with a speed field, which can't be hashed.
+    pub fn cache_key(&self, solver: Solver, refine: Option<Refine>) -> Option<u64> {
+        if self.field.is_some() {
+            return None;
+        }
+
+        let mut h = Fnv(0xcbf29ce484222325);
+
+        h.write_u32(VERSION);
+
+        match solver {
+            // Both give exactly the same path
+            Solver::Dp | Solver::Rolling => h.write_u8(0),
+            Solver::Dijkstra => h.write_u8(1),
+            Solver::Multigrid(options) => {
+                h.write_u8(2);
+                h.write_u64(options.coarsest as u64);
+                h.write_u64(options.factor as u64);
+                h.write_u32(options.corridor.to_bits());
+            }
+            Solver::Forward | Solver::FromStart => h.write_u8(3),
+        }
+
+        match refine {
+            None => h.write_u8(0),
+            Some(options) => {
+                h.write_u8(1);
+                h.write_u64(options.nodes as u64);
+                h.write_u64(options.max_iterations as u64);
+                h.write_u32(options.tolerance.to_bits());
+                h.write_u64(options.history as u64);
+            }
+        }
+
+        h.write_u64(self.grid.nx as u64);
+        h.write_u64(self.grid.ny as u64);
+        write_vec(&mut h, self.grid.spacing);
+        write_vec(&mut h, self.grid.origin);
+
+        write_vec(&mut h, self.start.cast());
+        write_vec(&mut h, self.end.cast());
+
+        h.write_u64(self.stencil.len() as u64);
+        for u in self.stencil.moves() {
+            write_vec(&mut h, u.cast());
+        }
+
+        h.write_u32(self.physics.friction.to_bits());
+        h.write_u32(self.physics.initial_speed.to_bits());
+        h.write_u32(self.physics.drag.to_bits());
+        h.write_u8(match self.physics.body {
+            Body::PointMass => 0,
+            Body::SolidSphere => 1,
+            Body::HollowSphere => 2,
+            Body::SolidDisk => 3,
+            Body::Ring => 4,
+        });
+
+        // Iteration order of the cells isn't deterministic
+        let mut cells: Vec<_> = self.obstacles.cells().collect();
+        cells.sort();
+
+        h.write_u64(cells.len() as u64);
+        for (i, j) in cells {
+            h.write_u64(i as u64);
+            h.write_u64(j as u64);
+        }
+
+        h.write_u64(self.obstacles.polygons().len() as u64);
+        for polygon in self.obstacles.polygons() {
+            h.write_u64(polygon.len() as u64);
+            for v in polygon {
+                write_vec(&mut h, *v);
+            }
+        }
+
+        Some(h.finish())
+    }
+
+    /// The solved path and its predicted time, to be stored in a [`PathCache`]. Must be called
+    /// after solving.
+    pub fn cached_path(&self) -> CachedPath {
+        let (time, _) = self.memo.get((0, self.start));
+
+        CachedPath {
+            time,
+            points: self.world_path(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::hash::Hasher;
+
+    use nalgebra::Vector2;
+
+    use super::{CachedPath, Fnv, PathCache};
+    use crate::{
+        Body, Brachistochrone, Grid, Multigrid, Obstacles, Physics, Refine, Solver, Stencil,
+    };
+
+    #[test]
+    fn cache_survives_a_round_trip() {
+        let mut cache = PathCache::with_capacity(2);
+
+        for key in 0..3 {
+            let path = CachedPath {
+                time: key as f32 + 0.5,
+                points: vec![Vector2::new(0., 10.), Vector2::new(key as f32, 2.)],
+            };
+
+            cache.insert(key, path);
+        }
+
+        let mut bytes = Vec::new();
+        cache.write_to(&mut bytes).unwrap();
+
+        let read = PathCache::read_from(bytes.as_slice()).unwrap();
+
+        // The oldest path made room for the others
+        assert_eq!(read.len(), 2);
+        assert_eq!(read.get(0), None);
+
+        for key in 1..3 {
+            assert_eq!(read.get(key), cache.get(key));
+        }
+
+        let mut rewritten = Vec::new();
+        read.write_to(&mut rewritten).unwrap();
+
+        assert_eq!(bytes, rewritten);
+    }
+
+    #[test]
+    fn keys_dont_depend_on_byte_order() {
+        let (mut a, mut b) = (Fnv(0xcbf29ce484222325), Fnv(0xcbf29ce484222325));
+
+        a.write_u32(0x01020304);
+        a.write_u64(0x05060708090a0b0c);
+        b.write(&[4, 3, 2, 1, 12, 11, 10, 9, 8, 7, 6, 5]);
+
+        assert_eq!(a.finish(), b.finish());
+    }
+
+    #[test]
+    fn keys_change_with_every_input() {
+        let (start, end) = (Vector2::new(0., 10.), Vector2::new(10., 2.));
+        let grid = Grid::fitted(start, end, 20, 0., 10.).unwrap();
+
+        let brac = |grid, end, stencil, physics, obstacles| {
+            Brachistochrone::new(grid, start, end, stencil, physics)
+                .unwrap()
+                .with_obstacles(obstacles)
+        };
+        let base = || {
+            brac(
+                grid,
+                end,
+                Stencil::default(),
+                Physics::default(),
+                Obstacles::new(),
+            )
+        };
+        let key = |brac: &Brachistochrone| brac.cache_key(Solver::Dp, None).unwrap();
+
+        let physics = |f: fn(&mut Physics)| {
+            let mut physics = Physics::default();
+            f(&mut physics);
+            brac(grid, end, Stencil::default(), physics, Obstacles::new())
+        };
+        let obstacles = |f: fn(&mut Obstacles)| {
+            let mut obstacles = Obstacles::new();
+            f(&mut obstacles);
+            brac(grid, end, Stencil::default(), Physics::default(), obstacles)
+        };
+
+        let keys = [
+            key(&base()),
+            key(&brac(
+                Grid::fitted(start, end, 21, 0., 10.).unwrap(),
+                end,
+                Stencil::default(),
+                Physics::default(),
+                Obstacles::new(),
+            )),
+            key(&brac(
+                grid,
+                Vector2::new(5., 5.),
+                Stencil::default(),
+                Physics::default(),
+                Obstacles::new(),
+            )),
+            key(&brac(
+                grid,
+                end,
+                Stencil::radius(4),
+                Physics::default(),
+                Obstacles::new(),
+            )),
+            key(&physics(|p| p.friction = 0.1)),
+            key(&physics(|p| p.initial_speed = 1.)),
+            key(&physics(|p| p.drag = 0.1)),
+            key(&physics(|p| p.body = Body::Ring)),
+            key(&obstacles(|o| {
+                o.block_cell(3, 3);
+            })),
+            key(&obstacles(|o| {
+                o.add_polygon(vec![
+                    Vector2::new(4., 4.),
+                    Vector2::new(6., 4.),
+                    Vector2::new(5., 6.),
+                ]);
+            })),
+            base().cache_key(Solver::Dijkstra, None).unwrap(),
+            base()
+                .cache_key(Solver::Multigrid(Multigrid::default()), None)
+                .unwrap(),
+            base().cache_key(Solver::Forward, None).unwrap(),
+            base()
+                .cache_key(Solver::Dp, Some(Refine::default()))
+                .unwrap(),
+        ];
+
+        assert_eq!(keys.iter().collect::<HashSet<_>>().len(), keys.len());
+
+        // Solvers giving the same path share their keys
+        assert_eq!(keys[0], base().cache_key(Solver::Rolling, None).unwrap());
+    }
+}
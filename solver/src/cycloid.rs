@@ -0,0 +1,148 @@
+use nalgebra::Vector2;
+
+use super::G;
+
+/// Number of segments used to approximate the cycloid when measuring distances
+const DISTANCE_SAMPLES: usize = 1024;
+
+/// Analytic solution of the Brachistochrone problem, used as a reference for the DP solver.
+///
+/// The curve is parametrized by the angle `theta` rolled by the generating circle:
+///
+/// x(theta) = x_start + r (theta - sin(theta))
+/// y(theta) = y_start - r (1 - cos(theta))
+pub struct Cycloid {
+    start: Vector2<f32>,
+    radius: f32,
+    theta_end: f32,
+}
+
+impl Cycloid {
+    /// Finds the cycloid going through `start` and `end`, if any. The end point must be to
+    /// the right of the start point and not above it.
+    pub fn new(start: Vector2<f32>, end: Vector2<f32>) -> Option<Cycloid> {
+        let dx = (end.x - start.x) as f64;
+        let dy = (start.y - end.y) as f64;
+
+        if dx <= 0. || dy < 0. {
+            return None;
+        }
+
+        // (theta - sin(theta)) / (1 - cos(theta)) increases monotonically from 0 to infinity
+        // on (0, 2pi), so the end angle can be found by bisection
+        let ratio = dx / dy;
+        let (mut lo, mut hi) = (0., std::f64::consts::TAU);
+
+        for _ in 0..100 {
+            let mid = 0.5 * (lo + hi);
+
+            if (mid - mid.sin()) / (1. - mid.cos()) < ratio {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+
+        let theta_end = 0.5 * (lo + hi);
+        let radius = dx / (theta_end - theta_end.sin());
+
+        Some(Cycloid {
+            start,
+            radius: radius as f32,
+            theta_end: theta_end as f32,
+        })
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    pub fn theta_end(&self) -> f32 {
+        self.theta_end
+    }
+
+    /// Exact descent time from the start point to the end point, starting at rest
+    pub fn time(&self) -> f32 {
+        self.theta_end * (self.radius / G).sqrt()
+    }
+
+    pub fn point(&self, theta: f32) -> Vector2<f32> {
+        self.start + self.radius * Vector2::new(theta - theta.sin(), -(1. - theta.cos()))
+    }
+
+    /// Samples `n + 1` points evenly spaced in `theta`, from the start point to the end point
+    pub fn sample(&self, n: usize) -> impl Iterator<Item = Vector2<f32>> {
+        (0..=n).map(move |i| self.point(self.theta_end * i as f32 / n as f32))
+    }
+
+    /// Distance from `p` to the closest point on the curve
+    pub fn distance_to(&self, p: Vector2<f32>) -> f32 {
+        let points: Vec<_> = self.sample(DISTANCE_SAMPLES).collect();
+
+        polyline_distance(p, &points)
+    }
+}
+
+/// Distance from `p` to the closest point on the polyline through `points`
+pub fn polyline_distance(p: Vector2<f32>, points: &[Vector2<f32>]) -> f32 {
+    if let [single] = points {
+        return (p - single).norm();
+    }
+
+    points
+        .windows(2)
+        .map(|w| {
+            let (a, b) = (w[0], w[1]);
+            let ab = b - a;
+            let len_sq = ab.norm_squared();
+
+            let t = if len_sq > 0. {
+                ((p - a).dot(&ab) / len_sq).clamp(0., 1.)
+            } else {
+                0.
+            };
+
+            (p - (a + t * ab)).norm()
+        })
+        .fold(f32::INFINITY, f32::min)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use nalgebra::Vector2;
+
+    use super::Cycloid;
+    use crate::{Brachistochrone, G, Grid, Physics, Stencil};
+
+    #[test]
+    fn half_turn_matches_closed_form() {
+        // Half a turn of the unit circle goes `pi` to the right and 2 down
+        let (start, end) = (Vector2::new(0., 0.), Vector2::new(PI, -2.));
+        let cycloid = Cycloid::new(start, end).unwrap();
+
+        assert!((cycloid.radius() - 1.).abs() < 1e-5);
+        assert!((cycloid.theta_end() - PI).abs() < 1e-5);
+        assert!((cycloid.time() - PI / G.sqrt()).abs() < 1e-5);
+        assert!((cycloid.point(cycloid.theta_end()) - end).norm() < 1e-5);
+    }
+
+    #[test]
+    fn dp_path_is_close_to_the_cycloid() {
+        let (start, end) = (Vector2::new(0., 10.), Vector2::new(10., 2.));
+        let grid = Grid::fitted(start, end, 30, 0., 10.).unwrap();
+
+        let mut brac =
+            Brachistochrone::new(grid, start, end, Stencil::radius(4), Physics::default()).unwrap();
+        brac.solve().unwrap();
+
+        let cmp = brac.compare_to_cycloid().unwrap();
+        let time = Cycloid::new(start, end).unwrap().time();
+
+        // The cycloid is optimal, the grid can only get close to it
+        assert!(cmp.time_error > -1e-4 * time, "{cmp:?}");
+        assert!(cmp.time_error < 1e-2 * time, "{cmp:?}");
+        assert!(cmp.max_distance < grid.spacing.max(), "{cmp:?}");
+    }
+}
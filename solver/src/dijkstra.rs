@@ -0,0 +1,111 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use nalgebra::Vector2;
+
+use super::progress::NODES_PER_UPDATE;
+use super::{Action, Brachistochrone, BrachistochroneError, BrachistochroneMemo, GridPoint};
+
+/// Node in the priority queue, ordered so that `BinaryHeap` pops the lowest value first
+pub(super) struct Candidate {
+    pub(super) value: f32,
+    pub(super) x: GridPoint,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.value.total_cmp(&self.value)
+    }
+}
+
+impl Brachistochrone {
+    /// Solves the problem as a shortest path problem over the grid nodes, by running
+    /// Dijkstra's algorithm backwards from the end point.
+    ///
+    /// Since the cost of a move doesn't depend on the stage `k`, the value-to-go of a node is the
+    /// same at every stage, so there's no need for a time horizon: paths can have any number of
+    /// moves, and the memo only takes `O(nx ny)` memory.
+    ///
+    /// With drag, or with friction and backward moves, this is the same as
+    /// [`Brachistochrone::solve_forward`].
+    pub fn solve_dijkstra(&mut self) -> Result<(), BrachistochroneError> {
+        if self.speed_depends_on_path() {
+            return self.solve_forward();
+        }
+
+        self.memo = BrachistochroneMemo::stationary(self.grid, self.stencil.len())?;
+        self.arrivals = None;
+        self.update_move_mask();
+
+        let mut visited = vec![false; self.grid.len()];
+        let mut settled = 0;
+        let mut queue = BinaryHeap::new();
+
+        self.progress.start(self.reachable_len());
+
+        self.memo.set((0, self.end), (0., Action::Terminal));
+        queue.push(Candidate {
+            value: 0.,
+            x: self.end,
+        });
+
+        while let Some(Candidate { value, x: x_next }) = queue.pop() {
+            let i = self.grid.node_index(x_next);
+
+            if visited[i] {
+                continue;
+            }
+
+            visited[i] = true;
+            settled += 1;
+
+            if settled % NODES_PER_UPDATE == 0 {
+                self.progress.check()?;
+                self.progress.advance(NODES_PER_UPDATE);
+            }
+
+            // Relax every move leading into `x_next`
+            for (u_idx, u) in self.stencil.moves().iter().enumerate() {
+                let x_k = x_next - u;
+
+                if *u == Vector2::zeros() || !self.grid.contains(x_k) || !self.allows(x_k, u_idx) {
+                    continue;
+                }
+
+                let c = self.cost(x_k, u);
+
+                if !c.is_finite() {
+                    continue;
+                }
+
+                let v_cur = value + c;
+                let (v_k, _) = self.memo.get((0, x_k));
+
+                if v_cur < v_k {
+                    self.memo.set((0, x_k), (v_cur, Action::Move(u_idx)));
+                    queue.push(Candidate {
+                        value: v_cur,
+                        x: x_k,
+                    });
+                }
+            }
+        }
+
+        self.progress.finish();
+        self.check_reachable()
+    }
+}
@@ -0,0 +1,44 @@
+use nalgebra::Vector2;
+
+use super::{Brachistochrone, G};
+
+/// Number of pieces each move is split into when integrating the descent time with drag
+const DRAG_STEPS: usize = 8;
+
+impl Brachistochrone {
+    /// Time taken to go from `a` to `b` (in world coordinates) along a straight segment,
+    /// arriving at `a` with speed `sqrt(w_a)`, along with the square of the speed at `b`.
+    /// The time is infinite or NaN if the body stops before getting to `b`.
+    ///
+    /// The tangential acceleration is `(A - drag v^2) / (1 + k)`, where `A` accounts for gravity
+    /// and friction and is constant along the segment, so `w = v^2` is given in closed form by
+    ///
+    /// w(s) = A / drag + (w_a - A / drag) exp(-2 drag s / (1 + k))
+    ///
+    /// The time is then integrated over a few pieces, within which the acceleration is taken
+    /// as constant, which also handles the body being at rest at either end.
+    pub(super) fn drag_move(&self, a: Vector2<f32>, b: Vector2<f32>, w_a: f32) -> (f32, f32) {
+        let len = (b - a).norm();
+        let inertia = 1. + self.physics.body.inertia_factor();
+        let accel = G * (a.y - b.y - self.physics.sliding_friction() * (b.x - a.x).abs()) / len;
+
+        let drag = self.physics.drag;
+        let w = |s: f32| match drag {
+            0. => w_a + 2. * accel * s / inertia,
+            _ => accel / drag + (w_a - accel / drag) * (-2. * drag * s / inertia).exp(),
+        };
+
+        let h = len / DRAG_STEPS as f32;
+        let mut v_prev = w_a.sqrt();
+        let mut time = 0.;
+
+        for i in 1..=DRAG_STEPS {
+            let v = w(h * i as f32).sqrt();
+
+            time += 2. * h / (v_prev + v);
+            v_prev = v;
+        }
+
+        (time, w(len))
+    }
+}
@@ -0,0 +1,45 @@
+use std::fmt;
+
+use nalgebra::Vector2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BrachistochroneError {
+    /// The given endpoint (in world coordinates) lies outside of the grid
+    OutOfBounds(Vector2<f32>),
+    /// The end point is higher than the body can get, given its initial speed
+    EndAboveStart,
+    /// Both endpoints are the same point, so there's nothing to fit a grid to
+    SameEndpoints,
+    /// No path from the start point reaches the end point, at least not within the time horizon
+    Unreachable,
+    /// The memo would take more memory than can be allocated, or the grid has more nodes than
+    /// can even be counted
+    AllocationTooLarge { bytes: usize },
+    /// The solve was stopped through [`super::Progress::cancel`]
+    Cancelled,
+}
+
+impl fmt::Display for BrachistochroneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BrachistochroneError::OutOfBounds(p) => {
+                write!(f, "point ({}, {}) is outside of the grid", p.x, p.y)
+            }
+            BrachistochroneError::EndAboveStart => {
+                write!(f, "end point is higher than the body can get")
+            }
+            BrachistochroneError::SameEndpoints => {
+                write!(f, "start and end points are the same")
+            }
+            BrachistochroneError::Unreachable => {
+                write!(f, "end point can't be reached from the start point")
+            }
+            BrachistochroneError::AllocationTooLarge { bytes } => {
+                write!(f, "failed to allocate {bytes} bytes for the memo")
+            }
+            BrachistochroneError::Cancelled => write!(f, "solve was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for BrachistochroneError {}
@@ -0,0 +1,284 @@
+use std::collections::BinaryHeap;
+
+use nalgebra::Vector2;
+
+use super::dijkstra::Candidate;
+use super::progress::NODES_PER_UPDATE;
+use super::{
+    Action, ActionTable, Brachistochrone, BrachistochroneError, BrachistochroneMemo, try_alloc,
+};
+
+/// Earliest arrival at every node, found by sweeping forward from the start point. Since nothing
+/// in it depends on the end point, it gives the path to any of them.
+pub(super) struct Arrivals {
+    time: Box<[f32]>,
+    // Move taken to get to each node, `Action::Terminal` for the start point
+    moves: ActionTable,
+}
+
+impl Brachistochrone {
+    /// Solves the problem by sweeping forward from the start point, carrying the speed the body
+    /// arrives at each node with. Needed once there's drag, since the speed at a node then
+    /// depends on the whole path taken to get there.
+    ///
+    /// Works like Dijkstra's algorithm, except that nodes are settled by arrival time from the
+    /// start point, and each node only keeps the speed of the earliest arrival. That isn't
+    /// necessarily optimal, since a later but faster arrival could make up for lost time
+    /// further along, but it's exact without drag and very close to it for moderate drag.
+    /// Unlike the other solvers, friction is also accounted for exactly on backward moves.
+    ///
+    /// With a speed field, drag is ignored and the travel time through the field is minimized
+    /// instead, as with the other solvers.
+    pub fn solve_forward(&mut self) -> Result<(), BrachistochroneError> {
+        self.arrivals = None;
+
+        let arrivals = self.sweep_forward(true)?;
+
+        self.follow_arrivals(&arrivals)
+    }
+
+    /// Same as [`Brachistochrone::solve_forward`], except that the sweep goes on past the end
+    /// point, until the earliest arrival at every node is known. Since the sweep doesn't
+    /// depend on the end point, moving it with [`Brachistochrone::set_end`] then takes no
+    /// solving at all, only following the arrivals back from the new end point.
+    pub fn solve_from_start(&mut self) -> Result<(), BrachistochroneError> {
+        let arrivals = self.sweep_forward(false)?;
+        let result = self.follow_arrivals(&arrivals);
+
+        self.arrivals = Some(arrivals);
+        result
+    }
+
+    /// Moves the end point to `end` (in world coordinates), snapping it to the closest node as
+    /// in [`Brachistochrone::new`], and finds the path to it. If the last solve was
+    /// [`Brachistochrone::solve_from_start`], that takes no solving at all, otherwise the
+    /// problem is solved with it, so that moving the end point again is quick.
+    pub fn set_end(&mut self, end: Vector2<f32>) -> Result<(), BrachistochroneError> {
+        if end.y > self.grid.to_world(self.start).y + self.physics.max_rise() {
+            return Err(BrachistochroneError::EndAboveStart);
+        }
+
+        let (node, error) = self
+            .grid
+            .snap(end)
+            .ok_or(BrachistochroneError::OutOfBounds(end))?;
+
+        self.end = node;
+        self.end_error = error;
+
+        match self.arrivals.take() {
+            Some(arrivals) => {
+                let result = self.follow_arrivals(&arrivals);
+
+                self.arrivals = Some(arrivals);
+                result
+            }
+            None => self.solve_from_start(),
+        }
+    }
+
+    /// Settles nodes by arrival time from the start point, up to the end point if `stop_at_end`
+    /// is set, and every reachable node otherwise
+    fn sweep_forward(&mut self, stop_at_end: bool) -> Result<Arrivals, BrachistochroneError> {
+        self.update_move_mask();
+
+        let len = self.grid.len();
+        let start = self.grid.node_index(self.start);
+
+        let mut time = try_alloc(len, f32::INFINITY)?;
+        let mut moves = ActionTable::new(len, self.stencil.len())?;
+        let mut speed_sq = vec![0.; len];
+        let mut visited = vec![false; len];
+        let mut settled = 0;
+        let mut queue = BinaryHeap::new();
+
+        self.progress.start(self.reachable_len());
+
+        time[start] = 0.;
+        moves.set(start, Action::Terminal);
+        speed_sq[start] = self.physics.initial_speed.powi(2);
+        queue.push(Candidate {
+            value: 0.,
+            x: self.start,
+        });
+
+        while let Some(Candidate { value, x: x_k }) = queue.pop() {
+            let i = self.grid.node_index(x_k);
+
+            if visited[i] {
+                continue;
+            }
+
+            visited[i] = true;
+            settled += 1;
+
+            if settled % NODES_PER_UPDATE == 0 {
+                self.progress.check()?;
+                self.progress.advance(NODES_PER_UPDATE);
+            }
+
+            if stop_at_end && x_k == self.end {
+                break;
+            }
+
+            for (u_idx, u) in self.stencil.moves().iter().enumerate() {
+                let x_next = x_k + u;
+
+                if *u == Vector2::zeros() || !self.grid.contains(x_next) || !self.allows(x_k, u_idx)
+                {
+                    continue;
+                }
+
+                let (dt, w) = match &self.field {
+                    Some(_) => (self.cost(x_k, u), 0.),
+                    None => self.drag_move(
+                        self.grid.to_world(x_k),
+                        self.grid.to_world(x_next),
+                        speed_sq[i],
+                    ),
+                };
+
+                let j = self.grid.node_index(x_next);
+                let t = value + dt;
+
+                if t < time[j] {
+                    time[j] = t;
+                    speed_sq[j] = w;
+                    moves.set(j, Action::Move(u_idx));
+                    queue.push(Candidate {
+                        value: t,
+                        x: x_next,
+                    });
+                }
+            }
+        }
+
+        self.progress.finish();
+
+        Ok(Arrivals { time, moves })
+    }
+
+    /// Fills the memo with the path to the end point given by `arrivals`
+    fn follow_arrivals(&mut self, arrivals: &Arrivals) -> Result<(), BrachistochroneError> {
+        self.memo = BrachistochroneMemo::stationary(self.grid, self.stencil.len())?;
+
+        let total = arrivals.time[self.grid.node_index(self.end)];
+
+        if !total.is_finite() {
+            return Err(BrachistochroneError::Unreachable);
+        }
+
+        // Only the nodes along the path get a memo entry, which is all `path_iter` needs
+        self.memo.set((0, self.end), (0., Action::Terminal));
+
+        let mut current = self.end;
+
+        while let Action::Move(u_idx) = arrivals.moves.get(self.grid.node_index(current)) {
+            let prev = current - self.stencil.moves()[u_idx];
+            let t = arrivals.time[self.grid.node_index(prev)];
+
+            self.memo.set((0, prev), (total - t, Action::Move(u_idx)));
+            current = prev;
+        }
+
+        self.check_reachable()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector2;
+
+    use crate::{Brachistochrone, Grid, Physics, Stencil};
+
+    #[test]
+    fn moving_the_end_point_matches_solving_forward() {
+        let start = Vector2::new(0., 10.);
+        let grid = Grid::fitted(start, Vector2::new(12., 0.), 30, 0., 10.).unwrap();
+        let ends = [
+            Vector2::new(10., 2.),
+            Vector2::new(5., 5.),
+            Vector2::new(12., 0.),
+            Vector2::new(3., 9.),
+        ];
+
+        for drag in [0., 0.05] {
+            let physics = Physics {
+                drag,
+                friction: 0.1,
+                ..Default::default()
+            };
+
+            let solver =
+                |end| Brachistochrone::new(grid, start, end, Stencil::radius(4), physics).unwrap();
+
+            let path = |brac: &Brachistochrone| -> Vec<_> {
+                brac.path_iter(brac.start_node())
+                    .unwrap()
+                    .map(|(v, x)| (v.to_bits(), x))
+                    .collect()
+            };
+
+            let mut swept = solver(ends[0]);
+            swept.solve_from_start().unwrap();
+
+            for end in ends {
+                swept.set_end(end).unwrap();
+
+                let mut fresh = solver(end);
+                fresh.solve_forward().unwrap();
+
+                assert_eq!(path(&swept), path(&fresh), "drag {drag}, end {end:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn moving_the_end_point_without_arrivals_solves_again() {
+        let (start, end) = (Vector2::new(0., 10.), Vector2::new(10., 2.));
+        let grid = Grid::fitted(start, Vector2::new(12., 0.), 20, 0., 10.).unwrap();
+        let solver = |end| {
+            Brachistochrone::new(grid, start, end, Stencil::radius(4), Physics::default()).unwrap()
+        };
+
+        let mut brac = solver(Vector2::new(5., 5.));
+        brac.set_end(end).unwrap();
+
+        let mut fresh = solver(end);
+        fresh.solve_forward().unwrap();
+
+        assert_eq!(brac.world_path(), fresh.world_path());
+        assert!(!brac.world_path().is_empty());
+    }
+
+    #[test]
+    fn backward_moves_with_friction_solve_forward() {
+        let (start, end) = (Vector2::new(0., 10.), Vector2::new(10., 2.));
+        let grid = Grid::fitted(start, end, 20, 0., 10.).unwrap();
+        let physics = Physics {
+            friction: 0.2,
+            ..Default::default()
+        };
+
+        let solver = || {
+            let stencil = Stencil::radius(4).with_backward();
+
+            Brachistochrone::new(grid, start, end, stencil, physics).unwrap()
+        };
+
+        let mut forward = solver();
+        forward.solve_forward().unwrap();
+
+        for solve in [
+            Brachistochrone::solve,
+            Brachistochrone::solve_rolling,
+            Brachistochrone::solve_dijkstra,
+        ] {
+            let mut brac = solver();
+            solve(&mut brac).unwrap();
+
+            assert_eq!(brac.world_path(), forward.world_path());
+            assert_eq!(brac.total_time(), forward.total_time());
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use nalgebra::Vector2;
+
+use super::BrachistochroneError;
+
+/// Error for grids with more nodes than fit in a `usize`
+const TOO_MANY_NODES: BrachistochroneError =
+    BrachistochroneError::AllocationTooLarge { bytes: usize::MAX };
+
+/// Grid coordinates of a node, `(0, 0)` being the bottom left one
+pub type GridPoint = Vector2<i32>;
+
+/// Discretization of the plane used by the solver. Grid coordinates go from `(0, 0)` to
+/// `(nx, ny)`, world coordinates are in metres.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Grid {
+    /// Number of cells along the `x` axis, the grid has `nx + 1` columns of nodes
+    pub nx: usize,
+    /// Number of cells along the `y` axis, the grid has `ny + 1` rows of nodes
+    pub ny: usize,
+    /// Distance between adjacent nodes along each axis
+    pub spacing: Vector2<f32>,
+    /// World coordinates of node `(0, 0)`
+    pub origin: Vector2<f32>,
+}
+
+impl Grid {
+    /// Square grid with `n` cells along each axis, covering `[0, n mu]^2`
+    pub fn square(n: usize, mu: f32) -> Grid {
+        Grid {
+            nx: n,
+            ny: n,
+            spacing: Vector2::new(mu, mu),
+            origin: Vector2::zeros(),
+        }
+    }
+
+    /// Grid with `resolution` cells along the longest side of the rectangle between `start`
+    /// and `end`, with the spacing along each axis chosen so that both are nodes. It extends
+    /// vertically to cover at least `bottom..=top` (as far as whole cells allow going below the
+    /// endpoints), since paths may go below or above them. Fails if both points are the same,
+    /// or if the grid would have too many nodes, see [`Grid::checked_len`].
+    pub fn fitted(
+        start: Vector2<f32>,
+        end: Vector2<f32>,
+        resolution: usize,
+        bottom: f32,
+        top: f32,
+    ) -> Result<Grid, BrachistochroneError> {
+        let res = resolution.max(1) as f32;
+
+        let dx = (end.x - start.x).abs();
+        let dy = (end.y - start.y).abs();
+        let longest = dx.max(dy);
+
+        if longest == 0. {
+            return Err(BrachistochroneError::SameEndpoints);
+        }
+
+        let steps = |d: f32| (res * d / longest).round().max(1.);
+
+        let spacing = match (dx, dy) {
+            (0., _) => Vector2::new(dy / steps(dy), dy / steps(dy)),
+            (_, 0.) => Vector2::new(dx / steps(dx), dx / steps(dx)),
+            _ => Vector2::new(dx / steps(dx), dy / steps(dy)),
+        };
+
+        let lowest = start.y.min(end.y);
+        let highest = top.max(start.y).max(end.y);
+
+        let ny_below = ((lowest - bottom) / spacing.y).floor().max(0.);
+        let ny_above = ((highest - lowest) / spacing.y).ceil();
+
+        let (nx, ny) = ((dx / spacing.x).round(), ny_below + ny_above);
+
+        // Casting would saturate, also rules out NaN
+        if !(nx < usize::MAX as f32 && ny < usize::MAX as f32) {
+            return Err(TOO_MANY_NODES);
+        }
+
+        let grid = Grid {
+            nx: nx as usize,
+            ny: ny as usize,
+            spacing,
+            origin: Vector2::new(start.x.min(end.x), lowest - ny_below * spacing.y),
+        };
+
+        grid.checked_len()?;
+
+        Ok(grid)
+    }
+
+    /// Grid covering (at least) the same area, with cells `scale` times larger along each axis
+    pub fn coarsened(&self, scale: usize) -> Grid {
+        Grid {
+            nx: self.nx.div_ceil(scale),
+            ny: self.ny.div_ceil(scale),
+            spacing: self.spacing * scale as f32,
+            origin: self.origin,
+        }
+    }
+
+    /// World coordinates of node `x`, which may lie outside of the grid
+    pub fn to_world(&self, x: GridPoint) -> Vector2<f32> {
+        self.origin + x.cast::<f32>().component_mul(&self.spacing)
+    }
+
+    /// Grid coordinates of `p`, which aren't necessarily integers
+    pub fn to_grid(&self, p: Vector2<f32>) -> Vector2<f32> {
+        (p - self.origin).component_div(&self.spacing)
+    }
+
+    /// Finds the node closest to `p`, along with its distance to `p` in metres. Returns `None`
+    /// if `p` lies outside of the grid (by more than half a cell), or isn't finite.
+    pub fn snap(&self, p: Vector2<f32>) -> Option<(GridPoint, f32)> {
+        let x = self.to_grid(p).map(f32::round);
+
+        // Also rules out NaN, which would otherwise be cast to 0
+        if !(0.0..=self.nx as f32).contains(&x.x) || !(0.0..=self.ny as f32).contains(&x.y) {
+            return None;
+        }
+
+        let node = x.map(|c| c as i32);
+
+        Some((node, (self.to_world(node) - p).norm()))
+    }
+
+    pub fn contains(&self, x: GridPoint) -> bool {
+        usize::try_from(x.x).is_ok_and(|i| i <= self.nx)
+            && usize::try_from(x.y).is_ok_and(|j| j <= self.ny)
+    }
+
+    /// Number of nodes. There's always at least one.
+    ///
+    /// # Panics
+    ///
+    /// If it doesn't fit in a `usize`, which [`super::Brachistochrone::new`] rules out.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.checked_len()
+            .unwrap_or_else(|_| panic!("grid of {} by {} cells is too large", self.nx, self.ny))
+    }
+
+    /// Same as [`Grid::len`], failing with `AllocationTooLarge` if the number of nodes doesn't fit
+    /// in a `usize`, since no memo could hold them anyway
+    pub fn checked_len(&self) -> Result<usize, BrachistochroneError> {
+        self.nx
+            .checked_add(1)
+            .zip(self.ny.checked_add(1))
+            .and_then(|(columns, rows)| columns.checked_mul(rows))
+            .ok_or(TOO_MANY_NODES)
+    }
+
+    /// Index of node `x` when nodes are laid out column by column, `None` if it lies outside of
+    /// the grid
+    pub fn checked_node_index(&self, x: GridPoint) -> Option<usize> {
+        self.contains(x)
+            .then(|| x.x as usize * (self.ny + 1) + x.y as usize)
+    }
+
+    /// Same as [`Grid::checked_node_index`], for nodes known to be in the grid.
+    ///
+    /// # Panics
+    ///
+    /// If `x` lies outside of the grid, see [`Grid::contains`].
+    pub fn node_index(&self, x: GridPoint) -> usize {
+        self.checked_node_index(x)
+            .unwrap_or_else(|| panic!("node {x:?} lies outside of the grid"))
+    }
+
+    /// Inverse of [`Grid::node_index`]
+    pub fn node(&self, i: usize) -> GridPoint {
+        debug_assert!(i < self.len());
+
+        Vector2::new((i / (self.ny + 1)) as i32, (i % (self.ny + 1)) as i32)
+    }
+}
@@ -0,0 +1,1021 @@
+//! Solver for the Brachistochrone problem (the curve of fastest descent between two points)
+//! by dynamic programming over a grid, along with the analytic solution and variations such as
+//! friction, drag, obstacles and general speed fields.
+
+use nalgebra::Vector2;
+
+mod cache;
+pub mod cycloid;
+mod dijkstra;
+mod drag;
+mod error;
+mod forward;
+mod grid;
+mod multigrid;
+mod obstacles;
+mod png;
+mod progress;
+mod refine;
+mod rolling;
+mod smooth;
+mod speed_field;
+mod stencil;
+mod svg;
+mod value_field;
+
+pub use cache::{CachedPath, PathCache};
+pub use error::BrachistochroneError;
+pub use grid::{Grid, GridPoint};
+pub use multigrid::Multigrid;
+pub use obstacles::Obstacles;
+pub use progress::Progress;
+pub use refine::{Refine, RefinedPath};
+pub use smooth::SmoothPath;
+pub use speed_field::{GravitySpeed, LayeredMedium, SpeedField};
+pub use stencil::Stencil;
+pub use svg::SvgOptions;
+pub use value_field::ValueField;
+
+use cycloid::{Cycloid, polyline_distance};
+use obstacles::MoveMask;
+
+const G: f32 = 9.81;
+
+/// Number of threads the solvers use by default
+fn available_threads() -> usize {
+    #[cfg(not(target_family = "wasm"))]
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+
+    // Threads aren't generally available on the web
+    #[cfg(target_family = "wasm")]
+    let threads = 1;
+
+    threads
+}
+
+/// Fills `out` with `f(i)` for every index `i`, splitting the work between `threads` threads.
+/// The results are the same as a serial loop, regardless of scheduling.
+fn fill_with<T: Send>(out: &mut [T], threads: usize, f: impl Fn(usize) -> T + Sync) {
+    if threads <= 1 {
+        for (i, x) in out.iter_mut().enumerate() {
+            *x = f(i);
+        }
+
+        return;
+    }
+
+    let chunk_len = out.len().div_ceil(threads).max(1);
+
+    std::thread::scope(|s| {
+        for (c, chunk) in out.chunks_mut(chunk_len).enumerate() {
+            let f = &f;
+
+            s.spawn(move || {
+                for (i, x) in chunk.iter_mut().enumerate() {
+                    *x = f(c * chunk_len + i);
+                }
+            });
+        }
+    });
+}
+
+/// Compares the polyline through `path`, which takes `path_time` to travel, against the
+/// reference one through `points`, which takes `time`
+fn compare_paths(
+    path: &[Vector2<f32>],
+    path_time: f32,
+    points: &[Vector2<f32>],
+    time: f32,
+) -> Option<PathComparison> {
+    if path.is_empty() || points.is_empty() {
+        return None;
+    }
+
+    let path_to_reference = path
+        .iter()
+        .map(|p| polyline_distance(*p, points))
+        .fold(0., f32::max);
+
+    let reference_to_path = points
+        .iter()
+        .map(|p| polyline_distance(*p, path))
+        .fold(0., f32::max);
+
+    Some(PathComparison {
+        time_error: path_time - time,
+        max_distance: path_to_reference.max(reference_to_path),
+    })
+}
+
+/// Allocates a slice of `len` copies of `value`, failing gracefully if there isn't enough memory
+fn try_alloc<T: Clone>(len: usize, value: T) -> Result<Box<[T]>, BrachistochroneError> {
+    let mut v = Vec::new();
+
+    v.try_reserve_exact(len)
+        .map_err(|_| BrachistochroneError::AllocationTooLarge {
+            bytes: len.saturating_mul(size_of::<T>()),
+        })?;
+    v.resize(len, value);
+
+    Ok(v.into())
+}
+
+/// An action u_k is either an index into the stencil or one of the two special values below
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Move(usize),
+    Uninit,
+    Terminal,
+}
+
+/// Integer types that can be used to store actions in the memo. The two largest
+/// values of each type are reserved for `Action::Uninit` and `Action::Terminal`.
+trait ActionIndex: Copy {
+    fn encode(a: Action) -> Self;
+    fn decode(self) -> Action;
+}
+
+macro_rules! impl_action_index {
+    ($($t:ty),*) => {
+        $(
+            impl ActionIndex for $t {
+                fn encode(a: Action) -> Self {
+                    match a {
+                        Action::Move(i) => i as $t,
+                        Action::Uninit => <$t>::MAX - 1,
+                        Action::Terminal => <$t>::MAX,
+                    }
+                }
+
+                fn decode(self) -> Action {
+                    match self {
+                        <$t>::MAX => Action::Terminal,
+                        x if x == <$t>::MAX - 1 => Action::Uninit,
+                        i => Action::Move(i as usize),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_action_index!(u8, u16, u32);
+
+/// Stores actions using the narrowest integer type able to represent every move in the stencil
+enum ActionTable {
+    U8(Box<[u8]>),
+    U16(Box<[u16]>),
+    U32(Box<[u32]>),
+}
+
+impl ActionTable {
+    fn new(len: usize, stencil_len: usize) -> Result<Self, BrachistochroneError> {
+        fn table<T: ActionIndex>(len: usize) -> Result<Box<[T]>, BrachistochroneError> {
+            try_alloc(len, T::encode(Action::Uninit))
+        }
+
+        // Leave room for the two reserved values
+        Ok(if stencil_len < u8::MAX as usize {
+            ActionTable::U8(table(len)?)
+        } else if stencil_len < u16::MAX as usize {
+            ActionTable::U16(table(len)?)
+        } else {
+            ActionTable::U32(table(len)?)
+        })
+    }
+
+    fn get(&self, i: usize) -> Action {
+        match self {
+            ActionTable::U8(t) => t[i].decode(),
+            ActionTable::U16(t) => t[i].decode(),
+            ActionTable::U32(t) => t[i].decode(),
+        }
+    }
+
+    fn set(&mut self, i: usize, a: Action) {
+        match self {
+            ActionTable::U8(t) => t[i] = ActionIndex::encode(a),
+            ActionTable::U16(t) => t[i] = ActionIndex::encode(a),
+            ActionTable::U32(t) => t[i] = ActionIndex::encode(a),
+        }
+    }
+
+    /// Sets each of the first `len` actions to `f(i)`, see [`fill_with`]
+    fn fill_with(&mut self, len: usize, threads: usize, f: impl Fn(usize) -> Action + Sync) {
+        match self {
+            ActionTable::U8(t) => fill_with(&mut t[..len], threads, |i| ActionIndex::encode(f(i))),
+            ActionTable::U16(t) => fill_with(&mut t[..len], threads, |i| ActionIndex::encode(f(i))),
+            ActionTable::U32(t) => fill_with(&mut t[..len], threads, |i| ActionIndex::encode(f(i))),
+        }
+    }
+}
+
+/// Value-to-go and optimal action for every `(k, x_k)` state
+struct BrachistochroneMemo {
+    grid: Grid,
+    // If set, the memo has a single layer shared by every stage `k`
+    stationary: bool,
+    // If set, the memo holds every state of the first stage, but only the state along a single
+    // path at every later stage, see `Brachistochrone::solve_rolling`
+    path: Option<Box<[GridPoint]>>,
+    values: Box<[f32]>,
+    actions: ActionTable,
+}
+
+impl BrachistochroneMemo {
+    fn new(
+        grid: Grid,
+        time_horizon: usize,
+        stencil_len: usize,
+    ) -> Result<Self, BrachistochroneError> {
+        Self::with_layers(grid, time_horizon + 1, false, stencil_len)
+    }
+
+    /// Memo for solvers where the value-to-go doesn't depend on the stage
+    fn stationary(grid: Grid, stencil_len: usize) -> Result<Self, BrachistochroneError> {
+        Self::with_layers(grid, 1, true, stencil_len)
+    }
+
+    fn with_layers(
+        grid: Grid,
+        layers: usize,
+        stationary: bool,
+        stencil_len: usize,
+    ) -> Result<Self, BrachistochroneError> {
+        let len = grid
+            .len()
+            .checked_mul(layers)
+            .ok_or(BrachistochroneError::AllocationTooLarge { bytes: usize::MAX })?;
+
+        Ok(Self {
+            grid,
+            stationary,
+            path: None,
+            values: try_alloc(len, f32::INFINITY)?,
+            actions: ActionTable::new(len, stencil_len)?,
+        })
+    }
+
+    /// Memo holding every state of the first stage, followed by the state `path[k]` of every
+    /// later stage `k`
+    fn along_path(
+        grid: Grid,
+        path: Box<[GridPoint]>,
+        stencil_len: usize,
+    ) -> Result<Self, BrachistochroneError> {
+        let len = grid.len() + path.len().saturating_sub(1);
+
+        Ok(Self {
+            grid,
+            stationary: false,
+            values: try_alloc(len, f32::INFINITY)?,
+            actions: ActionTable::new(len, stencil_len)?,
+            path: Some(path),
+        })
+    }
+
+    /// Index of the entry for `(k, x_k)`, `None` if the memo doesn't hold that state
+    fn offset(&self, (k, x_k): (usize, GridPoint)) -> Option<usize> {
+        if let Some(path) = &self.path {
+            return match k {
+                0 => Some(self.grid.node_index(x_k)),
+                _ => (path.get(k) == Some(&x_k)).then(|| self.grid.len() + k - 1),
+            };
+        }
+
+        let k = if self.stationary { 0 } else { k };
+
+        Some(k * self.grid.len() + self.grid.node_index(x_k))
+    }
+
+    fn get(&self, idx: (usize, GridPoint)) -> (f32, Action) {
+        match self.offset(idx) {
+            Some(i) => (self.values[i], self.actions.get(i)),
+            None => (f32::INFINITY, Action::Uninit),
+        }
+    }
+
+    fn set(&mut self, idx: (usize, GridPoint), entry: (f32, Action)) {
+        let i = self.offset(idx).expect("the memo holds the state");
+
+        self.set_at(i, entry);
+    }
+
+    fn set_at(&mut self, i: usize, (v, a): (f32, Action)) {
+        self.values[i] = v;
+        self.actions.set(i, a);
+    }
+}
+
+/// How far the DP solution is from a reference one, see [`Brachistochrone::compare_to_path`]
+#[derive(Debug, Clone, Copy)]
+pub struct PathComparison {
+    /// Time predicted by the DP minus the time along the reference path
+    pub time_error: f32,
+    /// Largest distance between the DP path and the reference path, in both directions
+    pub max_distance: f32,
+}
+
+/// Node of the solved path, along with the state of the body when reaching it, see
+/// [`Brachistochrone::path_samples`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathSample {
+    /// Stage `k` of the DP at which the node is reached
+    pub stage: usize,
+    pub node: GridPoint,
+    /// World coordinates of the node
+    pub position: Vector2<f32>,
+    /// Time elapsed since leaving the start point
+    pub elapsed: f32,
+    /// Time taken along the segment ending at this node, zero at the start point
+    pub segment_time: f32,
+    /// Speed of the body at the node
+    pub speed: f32,
+    /// Angle of the segment ending at this node above the horizontal, in radians, so it's
+    /// negative when going down. At the start point, that of the first segment.
+    pub angle: f32,
+}
+
+/// Shape of the body moving along the curve. Every body but a point mass rolls without
+/// slipping, so part of its kinetic energy goes into rotation.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Body {
+    /// Slides along the curve without rotating
+    #[default]
+    PointMass,
+    SolidSphere,
+    HollowSphere,
+    SolidDisk,
+    Ring,
+}
+
+impl Body {
+    pub const ALL: [Body; 5] = [
+        Body::PointMass,
+        Body::SolidSphere,
+        Body::HollowSphere,
+        Body::SolidDisk,
+        Body::Ring,
+    ];
+
+    /// Moment of inertia about the centre, divided by `m r^2`. The kinetic energy of the body
+    /// is `(1 + k) m v^2 / 2` when rolling without slipping.
+    pub fn inertia_factor(&self) -> f32 {
+        match self {
+            Body::PointMass => 0.,
+            Body::SolidSphere => 2. / 5.,
+            Body::HollowSphere => 2. / 3.,
+            Body::SolidDisk => 1. / 2.,
+            Body::Ring => 1.,
+        }
+    }
+}
+
+/// Physical parameters of the body moving along the curve
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Physics {
+    /// Coulomb (kinetic) friction coefficient between the body and the curve. Only a point mass
+    /// slides, so friction does no work on any other body.
+    pub friction: f32,
+    /// Speed of the body at the start point, in m/s. Its direction doesn't matter, since the
+    /// curve redirects the body right away.
+    pub initial_speed: f32,
+    pub body: Body,
+    /// Quadratic drag coefficient per unit mass, in 1/m, i.e. drag slows the body down by
+    /// `drag * v^2`. For a sphere, that's `rho C_d A / 2m`.
+    pub drag: f32,
+}
+
+impl Physics {
+    /// How far above the start point the body can get, if nothing slows it down
+    pub fn max_rise(&self) -> f32 {
+        (1. + self.body.inertia_factor()) * self.initial_speed.powi(2) / (2. * G)
+    }
+
+    fn sliding_friction(&self) -> f32 {
+        match self.body {
+            Body::PointMass => self.friction,
+            _ => 0.,
+        }
+    }
+}
+
+/// Solver to use with [`Brachistochrone::solve_with`]. They don't all give the same path, so
+/// it's part of [`Brachistochrone::cache_key`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Solver {
+    /// See [`Brachistochrone::solve`]
+    Dp,
+    /// See [`Brachistochrone::solve_rolling`]
+    Rolling,
+    /// See [`Brachistochrone::solve_dijkstra`]
+    Dijkstra,
+    /// See [`Brachistochrone::solve_multigrid`]
+    Multigrid(Multigrid),
+    /// See [`Brachistochrone::solve_forward`]
+    Forward,
+    /// See [`Brachistochrone::solve_from_start`]
+    FromStart,
+}
+
+pub struct Brachistochrone {
+    grid: Grid,
+    time_horizon: usize,
+    physics: Physics,
+    stencil: Stencil,
+    obstacles: Obstacles,
+    // Only computed when solving, if there are obstacles
+    move_mask: Option<MoveMask>,
+    // Nodes the path is restricted to, see `Brachistochrone::solve_multigrid`
+    region: Option<Box<[bool]>>,
+    // Replaces the speed given by `physics`, if set
+    field: Option<Box<dyn SpeedField>>,
+    progress: Progress,
+    // Every available thread if unset
+    threads: Option<usize>,
+    // Only kept by `Brachistochrone::solve_from_start`, so the end point can be moved
+    arrivals: Option<forward::Arrivals>,
+
+    // Grid coordinates of the nodes closest to the requested endpoints
+    start: GridPoint,
+    end: GridPoint,
+    // Distance between each requested endpoint and its node
+    start_error: f32,
+    end_error: f32,
+    memo: BrachistochroneMemo,
+}
+
+impl Brachistochrone {
+    /// Sets up the problem of going from `start` to `end` (in world coordinates) over `grid`. If
+    /// the endpoints aren't grid nodes, the closest nodes are used instead, see
+    /// [`Brachistochrone::snap_error`].
+    pub fn new(
+        grid: Grid,
+        start: Vector2<f32>,
+        end: Vector2<f32>,
+        stencil: Stencil,
+        physics: Physics,
+    ) -> Result<Brachistochrone, BrachistochroneError> {
+        if end.y > start.y + physics.max_rise() {
+            return Err(BrachistochroneError::EndAboveStart);
+        }
+
+        grid.checked_len()?;
+
+        let (start_node, start_error) = grid
+            .snap(start)
+            .ok_or(BrachistochroneError::OutOfBounds(start))?;
+        let (end_node, end_error) = grid
+            .snap(end)
+            .ok_or(BrachistochroneError::OutOfBounds(end))?;
+
+        let n = grid.nx.max(grid.ny);
+
+        // "Tighter" (not really) lower bound for time horizon found by approximating from experimental data
+        let time_horizon = match n {
+            1000.. => n / 5,
+            500.. => n / 4,
+            50.. => n / 3,
+            _ => n / 2,
+        };
+
+        // The actual memo is only allocated by the solver, since its size depends on it
+        let memo = BrachistochroneMemo::stationary(grid, stencil.len())?;
+
+        Ok(Self {
+            grid,
+            time_horizon,
+            physics,
+            stencil,
+            obstacles: Obstacles::default(),
+            move_mask: None,
+            region: None,
+            field: None,
+            progress: Progress::default(),
+            threads: None,
+            arrivals: None,
+
+            start: start_node,
+            end: end_node,
+            start_error,
+            end_error,
+            memo,
+        })
+    }
+
+    /// Forbids paths from going through `obstacles`
+    pub fn with_obstacles(mut self, obstacles: Obstacles) -> Self {
+        self.obstacles = obstacles;
+        self
+    }
+
+    /// Minimizes the travel time through `field` instead of the descent time under gravity,
+    /// which ignores the physical parameters of the problem. Note that the end point still
+    /// can't be higher than the body could get.
+    pub fn with_speed_field(mut self, field: impl SpeedField + 'static) -> Self {
+        self.field = Some(Box::new(field));
+        self
+    }
+
+    /// Reports the progress of every solve through `progress`, which can also cancel them
+    pub fn with_progress(mut self, progress: Progress) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Solves with `threads` threads instead of as many as are available. The results don't
+    /// depend on it.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads.max(1));
+        self
+    }
+
+    pub fn obstacles(&self) -> &Obstacles {
+        &self.obstacles
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    /// Grid coordinates of the node the paths start from
+    pub fn start_node(&self) -> GridPoint {
+        self.start
+    }
+
+    /// Grid coordinates of the node the paths end at
+    pub fn end_node(&self) -> GridPoint {
+        self.end
+    }
+
+    /// Largest distance (in metres) between one of the endpoints given to
+    /// [`Brachistochrone::new`] and the grid node used in its place
+    pub fn snap_error(&self) -> f32 {
+        self.start_error.max(self.end_error)
+    }
+
+    /// Speed of the body under gravity, with the physical parameters of the problem
+    pub fn gravity(&self) -> GravitySpeed {
+        GravitySpeed {
+            start: self.grid.to_world(self.start),
+            physics: self.physics,
+        }
+    }
+
+    /// Time taken to go from `a` to `b` (in world coordinates) along a straight segment
+    fn segment_time(&self, a: Vector2<f32>, b: Vector2<f32>) -> f32 {
+        match &self.field {
+            Some(field) => field.segment_time(a, b),
+            None => self.gravity().segment_time(a, b),
+        }
+    }
+
+    fn cost(&self, x_k: GridPoint, u: &GridPoint) -> f32 {
+        self.segment_time(self.grid.to_world(x_k), self.grid.to_world(x_k + u))
+    }
+
+    /// Whether the speed depends on the path taken, see [`Brachistochrone::solve_forward`]. That's
+    /// the case with drag, and with friction once the stencil has backward moves, see
+    /// [`GravitySpeed`].
+    fn speed_depends_on_path(&self) -> bool {
+        let backward = self.stencil.moves().iter().any(|u| u.x < 0);
+        let backward_friction = backward && self.physics.sliding_friction() > 0.;
+
+        (self.physics.drag > 0. || backward_friction) && self.field.is_none()
+    }
+
+    /// Solves the problem with a backward DP sweep over `time_horizon` stages. Uses
+    /// `O(nx ny time_horizon)` memory, see [`Brachistochrone::solve_rolling`] for the same
+    /// sweep using much less, and [`Brachistochrone::solve_dijkstra`] for an alternative.
+    ///
+    /// The speed at each state must only depend on the state itself, which isn't the case with
+    /// drag, or with friction and backward moves, so in those cases this is the same as
+    /// [`Brachistochrone::solve_forward`].
+    ///
+    /// Fails if the memo can't be allocated, if no path gets from the start point to the end
+    /// point within the time horizon, or if the solve is cancelled, see [`Progress`].
+    pub fn solve(&mut self) -> Result<(), BrachistochroneError> {
+        if self.speed_depends_on_path() {
+            return self.solve_forward();
+        }
+
+        let layer_len = self.grid.len();
+
+        self.memo = BrachistochroneMemo::new(self.grid, self.time_horizon, self.stencil.len())?;
+        self.arrivals = None;
+        self.update_move_mask();
+        self.memo
+            .set((self.time_horizon, self.end), (0., Action::Terminal));
+
+        let mut layer = vec![(f32::INFINITY, Action::Uninit); layer_len];
+
+        self.progress.start(self.time_horizon);
+
+        for k in (0..self.time_horizon).rev() {
+            self.progress.check()?;
+
+            // Every state in stage `k` only depends on stage `k + 1`, so they can all be solved
+            // independently. Their layout in the memo matches the one in `BrachistochroneMemo::offset`
+            let next = &self.memo.values[(k + 1) * layer_len..(k + 2) * layer_len];
+            fill_with(&mut layer, self.threads(), |i| {
+                self.best_action(self.grid.node(i), next)
+            });
+
+            for (i, entry) in layer.iter().enumerate() {
+                self.memo.set_at(k * layer_len + i, *entry);
+            }
+
+            self.progress.advance(1);
+        }
+
+        self.check_reachable()
+    }
+
+    /// Solves the problem with `solver`
+    pub fn solve_with(&mut self, solver: Solver) -> Result<(), BrachistochroneError> {
+        match solver {
+            Solver::Dp => self.solve(),
+            Solver::Rolling => self.solve_rolling(),
+            Solver::Dijkstra => self.solve_dijkstra(),
+            Solver::Multigrid(options) => self.solve_multigrid(options),
+            Solver::Forward => self.solve_forward(),
+            Solver::FromStart => self.solve_from_start(),
+        }
+    }
+
+    fn update_move_mask(&mut self) {
+        self.move_mask = (!self.obstacles.is_empty()).then(|| {
+            MoveMask::new(
+                &self.grid,
+                &self.stencil,
+                &self.obstacles,
+                self.region.as_deref(),
+                self.threads(),
+            )
+        });
+    }
+
+    fn threads(&self) -> usize {
+        self.threads.unwrap_or_else(available_threads)
+    }
+
+    /// Whether the move `u_idx` from `x_k` stays within the region the path is restricted to
+    /// (if any), and doesn't go through any obstacle
+    fn allows(&self, x_k: GridPoint, u_idx: usize) -> bool {
+        let node = self.grid.node_index(x_k);
+        let next = self.grid.node_index(x_k + self.stencil.moves()[u_idx]);
+
+        self.region.as_ref().is_none_or(|r| r[node] && r[next])
+            && self
+                .move_mask
+                .as_ref()
+                .is_none_or(|m| m.allows(node, u_idx))
+    }
+
+    /// Number of nodes a path could go through, i.e. those in the region if there's one
+    fn reachable_len(&self) -> usize {
+        match &self.region {
+            Some(region) => region.iter().filter(|r| **r).count(),
+            None => self.grid.len(),
+        }
+    }
+
+    fn check_reachable(&self) -> Result<(), BrachistochroneError> {
+        let (v, _) = self.memo.get((0, self.start));
+
+        match v.is_finite() {
+            true => Ok(()),
+            false => Err(BrachistochroneError::Unreachable),
+        }
+    }
+
+    /// Finds the action minimizing the value-to-go at `(k, x_k)`, given the value-to-go `next`
+    /// of every node at stage `k + 1`, laid out as in [`Grid::node_index`]
+    fn best_action(&self, x_k: GridPoint, next: &[f32]) -> (f32, Action) {
+        let mut min_v = f32::INFINITY;
+        let mut chosen_u = Action::Uninit;
+
+        for (u_idx, u) in self.stencil.moves().iter().enumerate() {
+            let x_k_next = x_k + u;
+
+            if !self.grid.contains(x_k_next) || !self.allows(x_k, u_idx) {
+                continue;
+            }
+
+            let v_next = next[self.grid.node_index(x_k_next)];
+
+            let c = self.cost(x_k, u);
+            let v_cur = c + v_next;
+
+            if v_cur < min_v {
+                min_v = v_cur;
+                chosen_u = Action::Move(u_idx);
+            }
+        }
+
+        (min_v, chosen_u)
+    }
+
+    /// Compares the solved DP path against the reference path through `points` (in world
+    /// coordinates), which takes `time` to travel. Must be called after
+    /// [`Brachistochrone::solve`]. All quantities are in world (unscaled) units.
+    pub fn compare_to_path(&self, points: &[Vector2<f32>], time: f32) -> Option<PathComparison> {
+        let (dp_time, _) = self.memo.get((0, self.start));
+
+        compare_paths(&self.world_path(), dp_time, points, time)
+    }
+
+    /// Compares the solved DP path against the cycloid through the same endpoints.
+    ///
+    /// Note that the cycloid is only the optimal curve in the absence of friction (for a point
+    /// mass) and drag, for a body starting at rest.
+    pub fn compare_to_cycloid(&self) -> Option<PathComparison> {
+        let (points, time) = self.cycloid_reference()?;
+
+        self.compare_to_path(&points, time)
+    }
+
+    /// Points along the cycloid through both endpoints, in world coordinates, and the time
+    /// taken along it
+    fn cycloid_reference(&self) -> Option<(Vec<Vector2<f32>>, f32)> {
+        let cycloid = Cycloid::new(self.grid.to_world(self.start), self.grid.to_world(self.end))?;
+        let samples = self.path_from(self.start).count().max(512);
+        let points = cycloid.sample(samples).collect();
+
+        // A rolling body is slower by the same factor everywhere along any curve, so the
+        // cycloid is still optimal
+        let time = cycloid.time() * (1. + self.physics.body.inertia_factor()).sqrt();
+
+        Some((points, time))
+    }
+
+    /// Compares the solved DP path against the path given by Snell's law through the same
+    /// endpoints, which is optimal when solving with `medium` as the speed field.
+    pub fn compare_to_snell(&self, medium: &LayeredMedium) -> Option<PathComparison> {
+        let points =
+            medium.snell_path(self.grid.to_world(self.start), self.grid.to_world(self.end))?;
+        let time = points
+            .windows(2)
+            .map(|w| medium.segment_time(w[0], w[1]))
+            .sum();
+
+        self.compare_to_path(&points, time)
+    }
+
+    /// Nodes of the solved path from the start point, in world coordinates
+    pub fn world_path(&self) -> Vec<Vector2<f32>> {
+        self.path_from(self.start)
+            .map(|(_, x_k)| self.grid.to_world(x_k))
+            .collect()
+    }
+
+    /// Smooth curve through the nodes of the solved path, see [`SmoothPath`]
+    pub fn smooth_path(&self) -> Option<SmoothPath> {
+        SmoothPath::new(self.world_path())
+    }
+
+    /// Time taken to travel along the polyline through `points` (in world coordinates), for a
+    /// body leaving the first point with the initial speed. NaN or infinite if it can't make it
+    /// to the end.
+    ///
+    /// Friction is accounted for as in the solver, which ignores the extra normal force due to
+    /// the curvature of the track, so the time is only approximate once there's friction.
+    pub fn time_along(&self, points: &[Vector2<f32>]) -> f32 {
+        let segments = points.windows(2).filter(|w| w[0] != w[1]);
+
+        if !self.speed_depends_on_path() {
+            return segments.map(|w| self.segment_time(w[0], w[1])).sum();
+        }
+
+        let mut speed_sq = self.physics.initial_speed.powi(2);
+        let mut time = 0.;
+
+        for w in segments {
+            let (dt, next_speed_sq) = self.drag_move(w[0], w[1], speed_sq);
+
+            time += dt;
+            speed_sq = next_speed_sq;
+        }
+
+        time
+    }
+
+    /// Nodes of the solved path from the start point, along with the time, speed and slope along
+    /// the way. Waiting at the end point (see [`Stencil`]) doesn't produce any samples. Must be
+    /// called after solving.
+    pub fn path_samples(&self) -> Vec<PathSample> {
+        let mut nodes = self.path_from(self.start).enumerate().peekable();
+        let total = nodes.peek().map_or(0., |(_, (v, _))| *v);
+
+        let mut samples: Vec<PathSample> = Vec::new();
+        let mut speed_sq = self.physics.initial_speed.powi(2);
+
+        for (stage, (v, node)) in nodes {
+            let position = self.grid.to_world(node);
+
+            let Some(prev) = samples.last() else {
+                samples.push(PathSample {
+                    stage,
+                    node,
+                    position,
+                    elapsed: 0.,
+                    segment_time: 0.,
+                    speed: match &self.field {
+                        Some(field) => field.speed(position),
+                        None => speed_sq.sqrt(),
+                    },
+                    angle: 0.,
+                });
+
+                continue;
+            };
+
+            if node == prev.node {
+                continue;
+            }
+
+            let d = position - prev.position;
+            let speed = if self.speed_depends_on_path() {
+                (_, speed_sq) = self.drag_move(prev.position, position, speed_sq);
+                speed_sq.sqrt()
+            } else {
+                match &self.field {
+                    Some(field) => field.speed(position),
+                    None => self.gravity().speed(position),
+                }
+            };
+
+            samples.push(PathSample {
+                stage,
+                node,
+                position,
+                elapsed: total - v,
+                segment_time: total - v - prev.elapsed,
+                speed,
+                angle: d.y.atan2(d.x),
+            });
+        }
+
+        if let [first, second, ..] = samples.as_mut_slice() {
+            first.angle = second.angle;
+        }
+
+        samples
+    }
+
+    /// Time taken along the solved path, `None` if it hasn't been solved or the end point can't
+    /// be reached
+    pub fn total_time(&self) -> Option<f32> {
+        let (v, _) = self.memo.get((0, self.start));
+
+        v.is_finite().then_some(v)
+    }
+
+    /// Length of the solved path, in metres
+    pub fn arc_length(&self) -> f32 {
+        self.world_path()
+            .windows(2)
+            .map(|w| (w[1] - w[0]).norm())
+            .sum()
+    }
+
+    /// Walks the solved path from `start`, yielding the value-to-go at each node along with its
+    /// grid coordinates. `None` if `start` lies outside of the grid. See
+    /// [`Brachistochrone::path_samples`] for the time and speed along the path.
+    pub fn path_iter(&self, start: GridPoint) -> Option<impl Iterator<Item = (f32, GridPoint)>> {
+        self.grid.contains(start).then(|| self.path_from(start))
+    }
+
+    /// Same as [`Brachistochrone::path_iter`], for a node known to be in the grid. Every move
+    /// from a node in the grid stays in it, so the path never leaves it.
+    fn path_from(&self, start: GridPoint) -> BrachistochronePathIterator<'_> {
+        BrachistochronePathIterator {
+            memo: &self.memo,
+            stencil: &self.stencil,
+            current: start,
+            finished: false,
+            k: 0,
+        }
+    }
+}
+
+struct BrachistochronePathIterator<'a> {
+    memo: &'a BrachistochroneMemo,
+    stencil: &'a Stencil,
+    current: GridPoint,
+    finished: bool,
+    k: usize,
+}
+
+impl<'a> Iterator for BrachistochronePathIterator<'a> {
+    type Item = (f32, GridPoint);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let x_k = self.current;
+        let (cost, u) = self.memo.get((self.k, x_k));
+
+        if self.finished {
+            return None;
+        }
+
+        match u {
+            Action::Uninit => return None,
+            Action::Terminal => self.finished = true,
+            Action::Move(u_idx) => {
+                self.current += self.stencil.moves()[u_idx];
+                self.k += 1;
+            }
+        }
+
+        Some((cost, x_k))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parallel_solve_matches_serial() {
+        let (start, end) = (Vector2::new(0., 10.), Vector2::new(12., 2.));
+        let grid = Grid::fitted(start, end, 16, 0., 10.).unwrap();
+        let physics = Physics {
+            friction: 0.1,
+            ..Default::default()
+        };
+
+        let mut obstacles = Obstacles::new();
+        obstacles.add_polygon(vec![
+            Vector2::new(4., 4.),
+            Vector2::new(6., 4.),
+            Vector2::new(5., 6.),
+        ]);
+
+        let solve = |threads| {
+            let mut brac = Brachistochrone::new(grid, start, end, Stencil::default(), physics)
+                .unwrap()
+                .with_obstacles(obstacles.clone())
+                .with_threads(threads);
+
+            brac.solve().unwrap();
+            brac.memo
+        };
+
+        let (serial, parallel) = (solve(1), solve(4));
+
+        let bits = |memo: &BrachistochroneMemo| -> Vec<_> {
+            memo.values.iter().map(|v| v.to_bits()).collect()
+        };
+        let actions = |memo: &BrachistochroneMemo| -> Vec<_> {
+            (0..memo.values.len())
+                .map(|i| memo.actions.get(i))
+                .collect()
+        };
+
+        assert_eq!(bits(&serial), bits(&parallel));
+        assert_eq!(actions(&serial), actions(&parallel));
+    }
+
+    #[test]
+    fn dijkstra_is_at_least_as_fast_as_the_staged_solve() {
+        let (start, end) = (Vector2::new(0., 10.), Vector2::new(12., 2.));
+        let grid = Grid::fitted(start, end, 24, 0., 10.).unwrap();
+        let physics = Physics {
+            friction: 0.1,
+            ..Default::default()
+        };
+
+        let mut obstacles = Obstacles::new();
+        obstacles.add_polygon(vec![
+            Vector2::new(4., 4.),
+            Vector2::new(6., 4.),
+            Vector2::new(5., 6.),
+        ]);
+
+        let solver = || {
+            Brachistochrone::new(grid, start, end, Stencil::radius(4), physics)
+                .unwrap()
+                .with_obstacles(obstacles.clone())
+        };
+
+        let (mut staged, mut dijkstra) = (solver(), solver());
+
+        staged.solve().unwrap();
+        dijkstra.solve_dijkstra().unwrap();
+
+        let time = dijkstra.total_time().unwrap();
+
+        // Paths with any number of moves are considered, including every path within the horizon
+        assert!(time <= staged.total_time().unwrap() * (1. + 1e-6));
+
+        // Too few stages for any path to get to the end point
+        let mut brac = solver();
+        brac.time_horizon = 1;
+
+        assert_eq!(brac.solve(), Err(BrachistochroneError::Unreachable));
+
+        brac.solve_dijkstra().unwrap();
+
+        assert_eq!(brac.total_time(), Some(time));
+    }
+}
@@ -0,0 +1,205 @@
+use nalgebra::Vector2;
+
+use super::{Brachistochrone, BrachistochroneError, Grid};
+
+/// Options for [`Brachistochrone::solve_multigrid`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Multigrid {
+    /// Minimum number of cells along the longest side of the coarsest grid
+    pub coarsest: usize,
+    /// How many times finer each grid is than the previous one, along each axis
+    pub factor: usize,
+    /// Half-width of the corridor around the path found on a grid, in cells of that grid
+    pub corridor: f32,
+}
+
+impl Default for Multigrid {
+    fn default() -> Self {
+        Multigrid {
+            coarsest: 50,
+            factor: 2,
+            corridor: 3.,
+        }
+    }
+}
+
+impl Brachistochrone {
+    /// Solves the problem on a sequence of increasingly finer grids, ending with the grid the
+    /// problem was set up with. The path found on each grid restricts the search on the next
+    /// one to a narrow corridor around it, so very fine grids can be solved quickly.
+    ///
+    /// Every grid is solved with [`Brachistochrone::solve_dijkstra`]. Since a path might exist
+    /// only on the finer grids, or outside of the corridor, a failed solve falls back to
+    /// searching the next grid entirely. A solve that succeeds isn't always optimal though: if the
+    /// path on a coarse grid goes around an obstacle on the other side than the best path on the
+    /// fine grid does, the corridor keeps the final path on that side, and it comes out slower
+    /// than the one [`Brachistochrone::solve_dijkstra`] finds. Only the last grid reports its
+    /// progress, since that's where most of the time goes, see [`super::Progress`].
+    pub fn solve_multigrid(&mut self, options: Multigrid) -> Result<(), BrachistochroneError> {
+        let factor = options.factor.max(2);
+        let n = self.grid.nx.max(self.grid.ny);
+
+        let mut scale = 1;
+
+        while n / (scale * factor) >= options.coarsest {
+            scale *= factor;
+        }
+
+        let start = self.grid.to_world(self.start);
+        let end = self.grid.to_world(self.end);
+
+        // Path found on the previous grid (in world coordinates), along with the corridor radius
+        let mut guide: Option<(Vec<Vector2<f32>>, f32)> = None;
+
+        while scale > 1 {
+            let grid = self.grid.coarsened(scale);
+
+            let mut coarse =
+                Brachistochrone::new(grid, start, end, self.stencil.clone(), self.physics)?
+                    .with_obstacles(self.obstacles.coarsened(scale));
+
+            coarse.progress = self.progress.cancellation();
+            coarse.threads = self.threads;
+            coarse.region = guide.as_ref().map(|(path, r)| corridor(&grid, path, *r));
+
+            guide = match coarse.solve_dijkstra() {
+                Ok(()) => Some((coarse.world_path(), options.corridor * max_spacing(&grid))),
+                Err(BrachistochroneError::Unreachable) => None,
+                Err(err) => return Err(err),
+            };
+
+            scale /= factor;
+        }
+
+        self.region = guide.map(|(path, r)| corridor(&self.grid, &path, r));
+
+        let result = match self.solve_dijkstra() {
+            Err(BrachistochroneError::Unreachable) if self.region.is_some() => {
+                self.region = None;
+                self.solve_dijkstra()
+            }
+            result => result,
+        };
+
+        // Later solves shouldn't be restricted to the corridor
+        self.region = None;
+
+        result
+    }
+}
+
+fn max_spacing(grid: &Grid) -> f32 {
+    grid.spacing.x.max(grid.spacing.y)
+}
+
+/// Marks every node of `grid` within distance `r` of the polyline through `path`
+fn corridor(grid: &Grid, path: &[Vector2<f32>], r: f32) -> Box<[bool]> {
+    let mut region = vec![false; grid.len()];
+
+    for w in path.windows(2) {
+        let (a, b) = (w[0], w[1]);
+        let ab = b - a;
+
+        // Only look at nodes in the bounding box of the segment, expanded by `r`
+        let min = grid.to_grid(a.inf(&b) - Vector2::repeat(r)).map(f32::floor);
+        let max = grid.to_grid(a.sup(&b) + Vector2::repeat(r)).map(f32::ceil);
+
+        let xs = min.x.max(0.) as i32..=max.x.clamp(0., grid.nx as f32) as i32;
+        let ys = min.y.max(0.) as i32..=max.y.clamp(0., grid.ny as f32) as i32;
+
+        for x in xs {
+            for y in ys.clone() {
+                let node = Vector2::new(x, y);
+                let p = grid.to_world(node);
+
+                let t = match ab.norm_squared() {
+                    0. => 0.,
+                    len_sq => ((p - a).dot(&ab) / len_sq).clamp(0., 1.),
+                };
+
+                if (a + t * ab - p).norm() <= r {
+                    region[grid.node_index(node)] = true;
+                }
+            }
+        }
+    }
+
+    region.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector2;
+
+    use super::Multigrid;
+    use crate::{Brachistochrone, Grid, Obstacles, Physics, Stencil};
+
+    const OPTIONS: Multigrid = Multigrid {
+        coarsest: 20,
+        factor: 2,
+        corridor: 3.,
+    };
+
+    /// Total times found by `solve_dijkstra` and `solve_multigrid`, in that order
+    fn solve_both(obstacles: &Obstacles) -> (f32, f32) {
+        let (start, end) = (Vector2::new(0., 10.), Vector2::new(12., 2.));
+        let grid = Grid::fitted(start, end, 80, 0., 10.).unwrap();
+
+        let solver = || {
+            Brachistochrone::new(grid, start, end, Stencil::radius(4), Physics::default())
+                .unwrap()
+                .with_obstacles(obstacles.clone())
+        };
+
+        let (mut dijkstra, mut multigrid) = (solver(), solver());
+
+        dijkstra.solve_dijkstra().unwrap();
+        multigrid.solve_multigrid(OPTIONS).unwrap();
+
+        (
+            dijkstra.total_time().unwrap(),
+            multigrid.total_time().unwrap(),
+        )
+    }
+
+    #[test]
+    fn multigrid_matches_dijkstra_without_obstacles() {
+        let (dijkstra, multigrid) = solve_both(&Obstacles::new());
+
+        assert!((multigrid - dijkstra).abs() <= 1e-5 * dijkstra);
+    }
+
+    #[test]
+    fn multigrid_places_blocked_cells_like_the_fine_grid() {
+        // Wall across the whole grid but for two gaps. With the cells in the wrong place on the
+        // coarse grids, the corridor used to lead the path through the slower gap.
+        let mut obstacles = Obstacles::new();
+
+        for j in (0..=80).filter(|j| !(24..27).contains(j) && !(44..47).contains(j)) {
+            for i in 4..6 {
+                obstacles.block_cell(i, j);
+            }
+        }
+
+        let (dijkstra, multigrid) = solve_both(&obstacles);
+
+        assert!((multigrid - dijkstra).abs() <= 1e-5 * dijkstra);
+    }
+
+    #[test]
+    fn multigrid_is_close_to_dijkstra_around_polygons() {
+        let mut obstacles = Obstacles::new();
+        obstacles.add_polygon(vec![
+            Vector2::new(3., 3.),
+            Vector2::new(5., 3.),
+            Vector2::new(5., 6.),
+            Vector2::new(3., 6.),
+        ]);
+
+        let (dijkstra, multigrid) = solve_both(&obstacles);
+
+        // Dijkstra searches the whole grid, so multigrid can't do any better
+        assert!(multigrid >= dijkstra * (1. - 1e-5));
+        assert!(multigrid <= dijkstra * 1.01);
+    }
+}
@@ -0,0 +1,381 @@
+use std::collections::HashSet;
+
+use nalgebra::Vector2;
+
+use super::{Grid, GridPoint, Stencil};
+
+const EPS: f32 = 1e-5;
+
+/// Regions the path isn't allowed to go through. Paths may still run along their boundaries.
+#[derive(Debug, Clone)]
+pub struct Obstacles {
+    // Cell `(i, j)` is the square between nodes `(i, j)` and `(i + 1, j + 1)`
+    cells: HashSet<(usize, usize)>,
+    // How many times finer the grid the cells belong to is than the one being solved, see
+    // `Obstacles::coarsened`
+    cell_scale: usize,
+    // In world coordinates
+    polygons: Vec<Vec<Vector2<f32>>>,
+}
+
+impl Default for Obstacles {
+    fn default() -> Self {
+        Obstacles {
+            cells: HashSet::new(),
+            cell_scale: 1,
+            polygons: Vec::new(),
+        }
+    }
+}
+
+impl Obstacles {
+    pub fn new() -> Obstacles {
+        Obstacles::default()
+    }
+
+    /// Blocks the grid cell whose bottom left corner is node `(i, j)`
+    pub fn block_cell(&mut self, i: usize, j: usize) -> &mut Self {
+        self.cells.insert((i, j));
+        self
+    }
+
+    /// Blocks the inside of a simple polygon, given by its vertices in world coordinates
+    pub fn add_polygon(&mut self, vertices: Vec<Vector2<f32>>) -> &mut Self {
+        self.polygons.push(vertices);
+        self
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize)> {
+        self.cells.iter().copied()
+    }
+
+    pub fn polygons(&self) -> &[Vec<Vector2<f32>>] {
+        &self.polygons
+    }
+
+    /// Every obstacle as a polygon in world coordinates, blocked cells included
+    pub fn to_polygons(&self, grid: &Grid) -> Vec<Vec<Vector2<f32>>> {
+        let scale = self.cell_scale as f32;
+
+        let cells = self.cells.iter().map(|&(i, j)| {
+            let (i, j) = (i as f32, j as f32);
+
+            [(i, j), (i + 1., j), (i + 1., j + 1.), (i, j + 1.)]
+                .into_iter()
+                .map(|(x, y)| {
+                    grid.origin + (Vector2::new(x, y) / scale).component_mul(&grid.spacing)
+                })
+                .collect()
+        });
+
+        self.polygons.iter().cloned().chain(cells).collect()
+    }
+
+    /// Same obstacles, for the grid coarsened by `scale` (see [`Grid::coarsened`]). The cells are
+    /// still those of the original grid, which are smaller than the cells of the coarse one.
+    pub(super) fn coarsened(&self, scale: usize) -> Obstacles {
+        Obstacles {
+            cell_scale: self.cell_scale * scale,
+            ..self.clone()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty() && self.polygons.is_empty()
+    }
+
+    /// Whether the segment between nodes `a` and `b` goes through the inside of an obstacle
+    pub fn blocks(&self, grid: &Grid, a: GridPoint, b: GridPoint) -> bool {
+        let polygons = self.grid_polygons(grid);
+
+        self.blocks_segment(&polygons, a, b)
+    }
+
+    /// Same as [`Obstacles::blocks`], for any segment, given in world coordinates
+    pub(super) fn blocks_world(&self, grid: &Grid, a: Vector2<f32>, b: Vector2<f32>) -> bool {
+        // Crossing a polygon doesn't depend on the coordinates, but the cells are in grid ones
+        self.blocks_cells(grid.to_grid(a), grid.to_grid(b))
+            || self.polygons.iter().any(|p| crosses_polygon(p, a, b))
+    }
+
+    /// Same as [`Obstacles::blocks`], with the polygons already in grid coordinates
+    fn blocks_segment(&self, polygons: &[Vec<Vector2<f32>>], a: GridPoint, b: GridPoint) -> bool {
+        let (a, b) = (a.cast::<f32>(), b.cast::<f32>());
+
+        self.blocks_cells(a, b) || polygons.iter().any(|p| crosses_polygon(p, a, b))
+    }
+
+    /// Polygons converted to grid coordinates. The mapping is affine, so it
+    /// doesn't change whether a segment and a polygon intersect.
+    fn grid_polygons(&self, grid: &Grid) -> Vec<Vec<Vector2<f32>>> {
+        self.polygons
+            .iter()
+            .map(|p| p.iter().map(|v| grid.to_grid(*v)).collect())
+            .collect()
+    }
+
+    fn blocks_cells(&self, a: Vector2<f32>, b: Vector2<f32>) -> bool {
+        if self.cells.is_empty() {
+            return false;
+        }
+
+        let (a, b) = (a * self.cell_scale as f32, b * self.cell_scale as f32);
+
+        let min = a.inf(&b).map(|c| c.floor().max(0.) as usize);
+        let max = a.sup(&b).map(|c| c.ceil().max(0.) as usize);
+
+        (min.x..=max.x)
+            .flat_map(|i| (min.y..=max.y).map(move |j| (i, j)))
+            .filter(|cell| self.cells.contains(cell))
+            .any(|(i, j)| {
+                let (i, j) = (i as f32, j as f32);
+                let square = [
+                    Vector2::new(i, j),
+                    Vector2::new(i + 1., j),
+                    Vector2::new(i + 1., j + 1.),
+                    Vector2::new(i, j + 1.),
+                ];
+
+                crosses_polygon(&square, a, b)
+            })
+    }
+}
+
+/// Which moves of the stencil are allowed from each grid node, given the obstacles
+pub(super) struct MoveMask {
+    stencil_len: usize,
+    // Bitset, one bit for each (node, move) pair
+    blocked: Box<[u64]>,
+}
+
+impl MoveMask {
+    /// If `region` is given, moves are only checked from the nodes in it
+    pub(super) fn new(
+        grid: &Grid,
+        stencil: &Stencil,
+        obstacles: &Obstacles,
+        region: Option<&[bool]>,
+        threads: usize,
+    ) -> MoveMask {
+        let polygons = obstacles.grid_polygons(grid);
+        let len = grid.len() * stencil.len();
+        let mut blocked = vec![0; len.div_ceil(64)];
+
+        super::fill_with(&mut blocked, threads, |w| {
+            let mut word = 0;
+
+            for i in (64 * w..64 * (w + 1)).take_while(|i| *i < len) {
+                let node = i / stencil.len();
+
+                if region.is_some_and(|r| !r[node]) {
+                    continue;
+                }
+
+                let a = grid.node(node);
+                let b = a + stencil.moves()[i % stencil.len()];
+
+                if obstacles.blocks_segment(&polygons, a, b) {
+                    word |= 1 << (i % 64);
+                }
+            }
+
+            word
+        });
+
+        MoveMask {
+            stencil_len: stencil.len(),
+            blocked: blocked.into(),
+        }
+    }
+
+    /// `node` is given by [`Grid::node_index`]
+    pub(super) fn allows(&self, node: usize, u_idx: usize) -> bool {
+        let i = node * self.stencil_len + u_idx;
+
+        self.blocked[i / 64] & (1 << (i % 64)) == 0
+    }
+}
+
+/// Whether the segment `ab` goes through the inside of `polygon`. Touching its boundary, or
+/// running along it, is fine.
+fn crosses_polygon(polygon: &[Vector2<f32>], a: Vector2<f32>, b: Vector2<f32>) -> bool {
+    let (min, max) = polygon.iter().fold(
+        (
+            Vector2::repeat(f32::INFINITY),
+            Vector2::repeat(f32::NEG_INFINITY),
+        ),
+        |(min, max), v| (min.inf(v), max.sup(v)),
+    );
+
+    if a.sup(&b).x <= min.x || a.inf(&b).x >= max.x || a.sup(&b).y <= min.y || a.inf(&b).y >= max.y
+    {
+        return false;
+    }
+
+    let d = b - a;
+
+    if d.norm_squared() == 0. {
+        return strictly_inside(polygon, a);
+    }
+
+    // Split the segment at every point where it meets the boundary: each piece is then
+    // either entirely inside, entirely outside or on the boundary itself
+    let mut ts = vec![0., 1.];
+
+    for (p, q) in edges(polygon) {
+        let e = q - p;
+        let denom = cross(d, e);
+
+        if denom.abs() > EPS {
+            let t = cross(p - a, e) / denom;
+            let s = cross(p - a, d) / denom;
+
+            if (-EPS..=1. + EPS).contains(&t) && (-EPS..=1. + EPS).contains(&s) {
+                ts.push(t.clamp(0., 1.));
+            }
+        } else if cross(p - a, d).abs() <= EPS {
+            // Collinear, split at the edge's endpoints
+            for v in [p, q] {
+                ts.push(((v - a).dot(&d) / d.norm_squared()).clamp(0., 1.));
+            }
+        }
+    }
+
+    ts.sort_by(f32::total_cmp);
+
+    ts.windows(2)
+        .filter(|w| w[1] - w[0] > EPS)
+        .any(|w| strictly_inside(polygon, a + 0.5 * (w[0] + w[1]) * d))
+}
+
+fn strictly_inside(polygon: &[Vector2<f32>], p: Vector2<f32>) -> bool {
+    let mut inside = false;
+
+    for (a, b) in edges(polygon) {
+        // Points on the boundary aren't inside
+        let ab = b - a;
+        let t = ((p - a).dot(&ab) / ab.norm_squared()).clamp(0., 1.);
+
+        if (a + t * ab - p).norm() <= EPS {
+            return false;
+        }
+
+        if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+            inside = !inside;
+        }
+    }
+
+    inside
+}
+
+fn edges(polygon: &[Vector2<f32>]) -> impl Iterator<Item = (Vector2<f32>, Vector2<f32>)> {
+    polygon
+        .iter()
+        .zip(polygon.iter().cycle().skip(1))
+        .map(|(p, q)| (*p, *q))
+}
+
+fn cross(a: Vector2<f32>, b: Vector2<f32>) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector2;
+
+    use super::{Obstacles, crosses_polygon, strictly_inside};
+    use crate::{Brachistochrone, Grid, Physics, Stencil};
+
+    fn square() -> Vec<Vector2<f32>> {
+        vec![
+            Vector2::new(0., 0.),
+            Vector2::new(2., 0.),
+            Vector2::new(2., 2.),
+            Vector2::new(0., 2.),
+        ]
+    }
+
+    fn crosses(a: (f32, f32), b: (f32, f32)) -> bool {
+        crosses_polygon(&square(), Vector2::new(a.0, a.1), Vector2::new(b.0, b.1))
+    }
+
+    #[test]
+    fn boundary_isnt_inside() {
+        assert!(strictly_inside(&square(), Vector2::new(1., 1.)));
+        assert!(!strictly_inside(&square(), Vector2::new(1., 0.)));
+        assert!(!strictly_inside(&square(), Vector2::new(2., 2.)));
+        assert!(!strictly_inside(&square(), Vector2::new(3., 1.)));
+    }
+
+    #[test]
+    fn segments_through_the_inside_cross() {
+        assert!(crosses((-1., 1.), (3., 1.)));
+        assert!(crosses((0.5, 0.5), (1.5, 1.5)));
+        assert!(crosses((1., 1.), (5., 5.)));
+        assert!(crosses((1., 1.), (1., 1.)));
+    }
+
+    #[test]
+    fn touching_the_boundary_doesnt_cross() {
+        // Ending on an edge, or at a corner
+        assert!(!crosses((1., -1.), (1., 0.)));
+        assert!(!crosses((-1., -1.), (0., 0.)));
+
+        // Touching a corner on the way past it
+        assert!(!crosses((-1., 1.), (1., 3.)));
+    }
+
+    #[test]
+    fn running_along_an_edge_doesnt_cross() {
+        assert!(!crosses((-1., 0.), (3., 0.)));
+        assert!(!crosses((2., 0.5), (2., 1.5)));
+        assert!(!crosses((0., 0.), (2., 0.)));
+    }
+
+    #[test]
+    fn crossing_a_corner_diagonally_crosses() {
+        // Through both opposite corners, along the diagonal
+        assert!(crosses((-1., -1.), (3., 3.)));
+
+        // Into the inside through a corner
+        assert!(crosses((-1., -1.), (1., 1.)));
+    }
+
+    #[test]
+    fn solved_path_avoids_obstacles() {
+        let (start, end) = (Vector2::new(0., 10.), Vector2::new(12., 2.));
+        let grid = Grid::fitted(start, end, 24, 0., 10.).unwrap();
+
+        let mut obstacles = Obstacles::new();
+        obstacles
+            .block_cell(6, 10)
+            .block_cell(6, 11)
+            .add_polygon(vec![
+                Vector2::new(3., 3.),
+                Vector2::new(6., 3.),
+                Vector2::new(6., 6.),
+                Vector2::new(3., 6.),
+            ]);
+
+        let mut brac =
+            Brachistochrone::new(grid, start, end, Stencil::radius(4), Physics::default())
+                .unwrap()
+                .with_obstacles(obstacles.clone());
+
+        brac.solve_dijkstra().unwrap();
+
+        let mut free =
+            Brachistochrone::new(grid, start, end, Stencil::radius(4), Physics::default()).unwrap();
+        free.solve_dijkstra().unwrap();
+
+        let blocked = |brac: &Brachistochrone| {
+            brac.world_path()
+                .windows(2)
+                .any(|w| obstacles.blocks_world(&grid, w[0], w[1]))
+        };
+
+        // Otherwise, the test wouldn't show anything
+        assert!(blocked(&free));
+        assert!(!blocked(&brac));
+    }
+}
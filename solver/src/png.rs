@@ -0,0 +1,110 @@
+use std::io::{self, Write};
+
+const SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+
+/// Largest amount of data in a stored (uncompressed) deflate block
+const MAX_BLOCK_LEN: usize = u16::MAX as usize;
+
+/// CRC-32 (ISO 3309) of every byte value, as used by PNG chunks
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut n = 0;
+
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+
+        while k < 8 {
+            c = if c & 1 == 1 {
+                0xedb88320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+
+        table[n] = c;
+        n += 1;
+    }
+
+    table
+};
+
+/// Writes an 8-bit RGB image, `pixels` holding 3 bytes per pixel, row by row from the top. The
+/// image data isn't compressed, which keeps the encoder tiny.
+pub(super) fn write_rgb(
+    mut w: impl Write,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+) -> io::Result<()> {
+    let row_len = 3 * width as usize;
+
+    debug_assert_eq!(pixels.len(), row_len * height as usize);
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // Bit depth, colour type (RGB), compression, filter and interlace methods
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    // Every row starts with its filter type, none here
+    let mut raw = Vec::with_capacity((row_len + 1) * height as usize);
+
+    for row in pixels.chunks(row_len.max(1)) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    w.write_all(SIGNATURE)?;
+    write_chunk(&mut w, b"IHDR", &header)?;
+    write_chunk(&mut w, b"IDAT", &zlib_stored(&raw))?;
+    write_chunk(&mut w, b"IEND", &[])?;
+
+    w.flush()
+}
+
+fn write_chunk(w: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    let crc = !kind.iter().chain(data).fold(!0, |c, b| {
+        CRC_TABLE[((c ^ *b as u32) & 0xff) as usize] ^ (c >> 8)
+    });
+
+    w.write_all(&(data.len() as u32).to_be_bytes())?;
+    w.write_all(kind)?;
+    w.write_all(data)?;
+    w.write_all(&crc.to_be_bytes())
+}
+
+/// zlib stream made of stored deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let blocks = data.len().div_ceil(MAX_BLOCK_LEN).max(1);
+    let mut out = Vec::with_capacity(data.len() + 5 * blocks + 6);
+
+    // Deflate with a 32K window, no preset dictionary, the check bits make it a multiple of 31
+    out.extend_from_slice(&[0x78, 0x01]);
+
+    for i in 0..blocks {
+        let block = &data[i * MAX_BLOCK_LEN..((i + 1) * MAX_BLOCK_LEN).min(data.len())];
+        let len = block.len() as u16;
+
+        out.push((i + 1 == blocks) as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+
+    let (a, b) = data.iter().fold((1, 0), |(a, b), x| {
+        let a = (a + *x as u32) % MOD;
+
+        (a, (b + a) % MOD)
+    });
+
+    (b << 16) | a
+}
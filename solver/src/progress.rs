@@ -0,0 +1,80 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use super::BrachistochroneError;
+
+/// Number of nodes the solvers based on Dijkstra's algorithm settle between progress updates
+pub(super) const NODES_PER_UPDATE: usize = 1024;
+
+/// Shared handle to follow a solve running on another thread, and to cancel it. Every clone
+/// refers to the same solve, see [`super::Brachistochrone::with_progress`].
+#[derive(Debug, Clone, Default)]
+pub struct Progress {
+    done: Arc<AtomicUsize>,
+    total: Arc<AtomicUsize>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Progress {
+    pub fn new() -> Progress {
+        Progress::default()
+    }
+
+    /// Steps done so far, out of the total. These are stages for [`super::Brachistochrone::solve`],
+    /// and nodes for the other solvers, which may stop before going through all of them.
+    pub fn steps(&self) -> (usize, usize) {
+        (
+            self.done.load(Ordering::Relaxed),
+            self.total.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Fraction of the solve done so far, from 0 to 1
+    pub fn fraction(&self) -> f32 {
+        match self.steps() {
+            (_, 0) => 0.,
+            (done, total) => (done as f32 / total as f32).min(1.),
+        }
+    }
+
+    /// Makes the solve stop as soon as possible, failing with [`BrachistochroneError::Cancelled`].
+    /// Later solves fail right away.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Handle sharing the cancellation flag, but not the progress. Used for the intermediate
+    /// solves of [`super::Brachistochrone::solve_multigrid`].
+    pub(super) fn cancellation(&self) -> Progress {
+        Progress {
+            done: Arc::default(),
+            total: Arc::default(),
+            cancelled: self.cancelled.clone(),
+        }
+    }
+
+    pub(super) fn start(&self, total: usize) {
+        self.done.store(0, Ordering::Relaxed);
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    pub(super) fn advance(&self, steps: usize) {
+        self.done.fetch_add(steps, Ordering::Relaxed);
+    }
+
+    pub(super) fn finish(&self) {
+        self.done
+            .store(self.total.load(Ordering::Relaxed), Ordering::Relaxed);
+    }
+
+    pub(super) fn check(&self) -> Result<(), BrachistochroneError> {
+        match self.is_cancelled() {
+            true => Err(BrachistochroneError::Cancelled),
+            false => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,323 @@
+use std::collections::VecDeque;
+use std::iter;
+
+use nalgebra::Vector2;
+
+use super::{Brachistochrone, PathComparison, compare_paths};
+
+/// Most times a step is halved before the line search gives up
+const MAX_BACKTRACKS: usize = 40;
+
+/// Fraction of the decrease predicted by the gradient a step must achieve to be accepted
+const ARMIJO: f64 = 1e-4;
+
+/// Options for [`Brachistochrone::refine`]
+#[derive(Debug, Clone, Copy)]
+pub struct Refine {
+    /// Segments of the DP path are split until it has at least this many nodes, since a polyline
+    /// through only a few of them can't get very close to a smooth curve
+    pub nodes: usize,
+    pub max_iterations: usize,
+    /// Stop once an iteration improves the time by less than this fraction of it. The time is
+    /// very flat around the optimum, so stopping early leaves the path visibly off.
+    pub tolerance: f32,
+    /// Number of past steps L-BFGS approximates the inverse Hessian with
+    pub history: usize,
+}
+
+impl Default for Refine {
+    fn default() -> Self {
+        Self {
+            nodes: 64,
+            max_iterations: 500,
+            tolerance: 1e-9,
+            history: 8,
+        }
+    }
+}
+
+/// Path given by [`Brachistochrone::refine`]
+#[derive(Debug, Clone)]
+pub struct RefinedPath {
+    /// Nodes of the path in world coordinates, from the start point to the end point
+    pub points: Vec<Vector2<f32>>,
+    /// Time along the DP path, as given by [`Brachistochrone::time_along`]
+    pub dp_time: f32,
+    /// Time along the refined path
+    pub time: f32,
+    pub iterations: usize,
+}
+
+impl RefinedPath {
+    /// How much faster the refined path is than the DP one, in seconds
+    pub fn improvement(&self) -> f32 {
+        self.dp_time - self.time
+    }
+}
+
+impl Brachistochrone {
+    /// Refines the solved path by moving its nodes off the grid, minimizing the time along it
+    /// with L-BFGS. The endpoints stay where they are, and the DP path is only a starting
+    /// point, so the refined path isn't limited to the directions of the stencil anymore. Must
+    /// be called after solving.
+    ///
+    /// The gradient is found by finite differences, which only involve the two segments around
+    /// each node, except when the speed depends on the path (with drag, or with friction and
+    /// backward moves), where moving a node changes the speed along the rest of the path. Steps that would take the path through an obstacle are rejected.
+    ///
+    /// Returns `None` if there's no path, or if the body can't make it along the DP path.
+    pub fn refine(&self, options: Refine) -> Option<RefinedPath> {
+        let mut path = self.world_path();
+        path.dedup();
+
+        let path = subdivide(&path, options.nodes);
+        let (&first, &last) = (path.first()?, path.last()?);
+
+        // Splitting the segments doesn't change the time along the path
+        let dp_time = self.time_along(&path);
+
+        if !dp_time.is_finite() {
+            return None;
+        }
+
+        let to_points = |x: &[f64]| -> Vec<_> {
+            let interior = x.chunks(2).map(|c| Vector2::new(c[0] as f32, c[1] as f32));
+
+            iter::once(first)
+                .chain(interior)
+                .chain(iter::once(last))
+                .collect()
+        };
+
+        let mut x: Vec<f64> = path[1..path.len().max(2) - 1]
+            .iter()
+            .flat_map(|p| [p.x as f64, p.y as f64])
+            .collect();
+
+        let spacing = self.grid.spacing.min() as f64;
+        let mut f = self.refined_time(&path);
+        let mut g = self.time_gradient(&path, spacing * 1e-2);
+
+        // Past steps and the change in the gradient along them, most recent first
+        let mut history: VecDeque<(Vec<f64>, Vec<f64>)> = VecDeque::new();
+        let mut iterations = 0;
+
+        while iterations < options.max_iterations && !x.is_empty() {
+            iterations += 1;
+
+            let mut d = lbfgs_direction(&g, &history);
+            let mut slope = dot(&g, &d);
+
+            if slope >= 0. {
+                history.clear();
+                d = g.iter().map(|g| -g).collect();
+                slope = dot(&g, &d);
+            }
+
+            if slope == 0. {
+                break;
+            }
+
+            // Without any history, the gradient says nothing about how far to go, so start by
+            // moving the nodes by about a cell
+            let mut t = match history.is_empty() {
+                true => spacing / d.iter().fold(0., |m, d| d.abs().max(m)),
+                false => 1.,
+            };
+
+            let mut accepted = None;
+
+            for _ in 0..MAX_BACKTRACKS {
+                let x_new: Vec<_> = x.iter().zip(&d).map(|(x, d)| x + t * d).collect();
+                let f_new = self.refined_time(&to_points(&x_new));
+
+                if f_new <= f + ARMIJO * t * slope {
+                    accepted = Some((x_new, f_new));
+                    break;
+                }
+
+                t /= 2.;
+            }
+
+            let Some((x_new, f_new)) = accepted else {
+                break;
+            };
+
+            let g_new = self.time_gradient(&to_points(&x_new), spacing * 1e-2);
+
+            let s: Vec<_> = x_new.iter().zip(&x).map(|(a, b)| a - b).collect();
+            let y: Vec<_> = g_new.iter().zip(&g).map(|(a, b)| a - b).collect();
+
+            // Otherwise the update wouldn't keep the approximation positive definite
+            if dot(&s, &y) > 0. {
+                history.push_front((s, y));
+                history.truncate(options.history);
+            }
+
+            let converged = f - f_new < options.tolerance as f64 * f;
+
+            (x, f, g) = (x_new, f_new, g_new);
+
+            if converged {
+                break;
+            }
+        }
+
+        Some(RefinedPath {
+            points: to_points(&x),
+            dp_time,
+            time: f as f32,
+            iterations,
+        })
+    }
+
+    /// Compares `refined` against the cycloid through the same endpoints, see
+    /// [`Brachistochrone::compare_to_cycloid`]
+    pub fn compare_refined_to_cycloid(&self, refined: &RefinedPath) -> Option<PathComparison> {
+        let (points, time) = self.cycloid_reference()?;
+
+        compare_paths(&refined.points, refined.time, &points, time)
+    }
+
+    /// Time along the path through `points`, infinite if it goes through an obstacle. Near the
+    /// optimum, moving the nodes barely changes it, so the segments are summed in `f64`.
+    fn refined_time(&self, points: &[Vector2<f32>]) -> f64 {
+        let blocked = points
+            .windows(2)
+            .any(|w| self.obstacles.blocks_world(&self.grid, w[0], w[1]));
+
+        match blocked {
+            true => f64::INFINITY,
+            false if self.speed_depends_on_path() => self.time_along(points) as f64,
+            false => points
+                .windows(2)
+                .map(|w| self.segment_time(w[0], w[1]) as f64)
+                .sum(),
+        }
+    }
+
+    /// Gradient of the time along the path through `points` with respect to the coordinates of
+    /// its interior nodes, by central differences with a step of `h`
+    fn time_gradient(&self, points: &[Vector2<f32>], h: f64) -> Vec<f64> {
+        let mut points = points.to_vec();
+        let mut gradient = Vec::with_capacity(2 * points.len().saturating_sub(2));
+        let path_dependent = self.speed_depends_on_path();
+
+        for i in 1..points.len().saturating_sub(1) {
+            for c in 0..2 {
+                let p = points[i];
+
+                // Unless the speed depends on the path, only the segments on either side of the
+                // node depend on it
+                let time = |points: &mut [Vector2<f32>], offset: f64| {
+                    points[i][c] = (p[c] as f64 + offset) as f32;
+
+                    let t = match path_dependent {
+                        true => self.time_along(points),
+                        false => self.time_along(&points[i - 1..=i + 1]),
+                    };
+
+                    points[i] = p;
+                    t as f64
+                };
+
+                let (before, after) = (time(&mut points, -h), time(&mut points, h));
+
+                // Near the height of the start point, moving a node up can leave the body without
+                // enough energy to get there, in which case fall back to a one-sided difference
+                let d = match (before.is_finite(), after.is_finite()) {
+                    (true, true) => (after - before) / (2. * h),
+                    (true, false) => (time(&mut points, 0.) - before) / h,
+                    (false, true) => (after - time(&mut points, 0.)) / h,
+                    (false, false) => 0.,
+                };
+
+                gradient.push(d);
+            }
+        }
+
+        gradient
+    }
+}
+
+/// Splits every segment of the polyline through `points` into equal parts, so that it ends up
+/// with at least `nodes` nodes
+fn subdivide(points: &[Vector2<f32>], nodes: usize) -> Vec<Vector2<f32>> {
+    let segments = points.len().saturating_sub(1);
+
+    if segments == 0 {
+        return points.to_vec();
+    }
+
+    let parts = nodes.saturating_sub(1).div_ceil(segments).max(1);
+
+    points
+        .windows(2)
+        .flat_map(|w| (0..parts).map(move |i| w[0].lerp(&w[1], i as f32 / parts as f32)))
+        .chain(points.last().copied())
+        .collect()
+}
+
+/// Direction of the next step given the gradient `g`, by the L-BFGS two-loop recursion
+fn lbfgs_direction(g: &[f64], history: &VecDeque<(Vec<f64>, Vec<f64>)>) -> Vec<f64> {
+    let mut q: Vec<_> = g.to_vec();
+    let mut alphas = Vec::with_capacity(history.len());
+
+    for (s, y) in history {
+        let alpha = dot(s, &q) / dot(s, y);
+
+        q.iter_mut().zip(y).for_each(|(q, y)| *q -= alpha * y);
+        alphas.push(alpha);
+    }
+
+    // Scale by the curvature along the most recent step
+    if let Some((s, y)) = history.front() {
+        let gamma = dot(s, y) / dot(y, y);
+
+        q.iter_mut().for_each(|q| *q *= gamma);
+    }
+
+    for ((s, y), alpha) in history.iter().zip(alphas).rev() {
+        let beta = dot(y, &q) / dot(s, y);
+
+        q.iter_mut()
+            .zip(s)
+            .for_each(|(q, s)| *q += (alpha - beta) * s);
+    }
+
+    q.iter_mut().for_each(|q| *q = -*q);
+    q
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector2;
+
+    use super::Refine;
+    use crate::{Brachistochrone, Grid, Physics, Stencil};
+
+    #[test]
+    fn refined_path_gets_closer_to_the_cycloid() {
+        let (start, end) = (Vector2::new(0., 10.), Vector2::new(10., 2.));
+        let grid = Grid::fitted(start, end, 30, 0., 10.).unwrap();
+
+        let mut brac =
+            Brachistochrone::new(grid, start, end, Stencil::radius(4), Physics::default()).unwrap();
+        brac.solve().unwrap();
+
+        let refined = brac.refine(Refine::default()).unwrap();
+        let dp = brac.compare_to_cycloid().unwrap();
+        let cmp = brac.compare_refined_to_cycloid(&refined).unwrap();
+
+        assert!(refined.improvement() >= 0.);
+        assert!(cmp.max_distance < dp.max_distance / 5., "{dp:?} {cmp:?}");
+        assert!(
+            cmp.time_error.abs() < dp.time_error.abs() / 10.,
+            "{dp:?} {cmp:?}"
+        );
+    }
+}
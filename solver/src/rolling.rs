@@ -0,0 +1,230 @@
+use std::ops::Range;
+
+use super::{
+    Action, Brachistochrone, BrachistochroneError, BrachistochroneMemo, GridPoint, fill_with,
+    try_alloc,
+};
+
+/// Path being followed by [`Brachistochrone::follow_stages`], written into the memo as it goes
+struct Trace {
+    memo: BrachistochroneMemo,
+    path: Vec<GridPoint>,
+    x_k: GridPoint,
+}
+
+impl Brachistochrone {
+    /// Solves the problem with the same backward DP sweep as [`Brachistochrone::solve`], giving
+    /// exactly the same path, while only keeping a few stages in memory at a time.
+    ///
+    /// The path is followed forward from the start point, and the stages it needs are found by
+    /// recursive checkpointing: to follow stages `lo..hi` given the value-to-go at stage `hi`,
+    /// the stages down to the middle one `mid` are solved again, two at a time, after which
+    /// `lo..mid` is followed from `mid` and `mid..hi` from `hi`. Only one stage per level of
+    /// recursion is kept, so the peak is about `log2(time_horizon) + 3` layers of values, i.e.
+    /// `O(nx ny log(time_horizon))` memory instead of `O(nx ny time_horizon)`. In exchange, every
+    /// stage is solved about `log2(time_horizon) / 2` times. Only the first stage and the states
+    /// along the path end up in the memo, which is all [`Brachistochrone::path_iter`] and
+    /// [`Brachistochrone::value_field`] need.
+    ///
+    /// With drag, or with friction and backward moves, this is the same as
+    /// [`Brachistochrone::solve_forward`].
+    pub fn solve_rolling(&mut self) -> Result<(), BrachistochroneError> {
+        if self.speed_depends_on_path() {
+            return self.solve_forward();
+        }
+
+        let horizon = self.time_horizon;
+        let len = self.grid.len();
+
+        // Drop the previous memo before allocating anything, it could be huge
+        self.memo = BrachistochroneMemo::along_path(self.grid, Box::new([]), self.stencil.len())?;
+        self.arrivals = None;
+        self.update_move_mask();
+        self.progress.start(stage_solves(horizon));
+
+        // The path has a state at every stage, so the memo can be filled in as it's followed
+        let path = vec![self.start; horizon + 1];
+        let mut trace = Trace {
+            memo: BrachistochroneMemo::along_path(self.grid, path.into(), self.stencil.len())?,
+            path: Vec::with_capacity(horizon + 1),
+            x_k: self.start,
+        };
+
+        let mut last = try_alloc(len, f32::INFINITY)?;
+        let mut scratch = try_alloc(len, f32::INFINITY)?;
+
+        self.terminal_stage(&mut last);
+        self.follow_stages(0..horizon, &last, &mut scratch, &mut trace)?;
+
+        let Trace {
+            mut memo,
+            mut path,
+            x_k,
+        } = trace;
+
+        path.push(x_k);
+
+        // Laid out as in `BrachistochroneMemo::offset`
+        if horizon > 0 {
+            memo.set_at(len + horizon - 1, (0., Action::Terminal));
+        }
+
+        memo.path = Some(path.into());
+        self.memo = memo;
+
+        self.progress.finish();
+        self.check_reachable()
+    }
+
+    /// Follows the path through `stages`, given the value-to-go `last` of every node at the stage
+    /// right after them, see [`Brachistochrone::solve_rolling`]. `scratch` holds a layer of
+    /// values the stages are solved in.
+    fn follow_stages(
+        &self,
+        stages: Range<usize>,
+        last: &[f32],
+        scratch: &mut [f32],
+        trace: &mut Trace,
+    ) -> Result<(), BrachistochroneError> {
+        if stages.len() > 1 {
+            let mid = stages.start + stages.len() / 2;
+            let mut middle = try_alloc(last.len(), f32::INFINITY)?;
+
+            self.solve_stages(&mut middle, scratch, last, stages.end - mid)?;
+            self.follow_stages(stages.start..mid, &middle, scratch, trace)?;
+            drop(middle);
+
+            return self.follow_stages(mid..stages.end, last, scratch, trace);
+        }
+
+        let Some(k) = stages.last() else {
+            return Ok(());
+        };
+
+        let len = self.grid.len();
+
+        if k == 0 {
+            let memo = &mut trace.memo;
+            let threads = self.threads();
+            let best = |i| self.best_action(self.grid.node(i), last);
+
+            fill_with(&mut memo.values[..len], threads, |i| best(i).0);
+            memo.actions.fill_with(len, threads, |i| best(i).1);
+
+            if !memo.values[self.grid.node_index(self.start)].is_finite() {
+                return Err(BrachistochroneError::Unreachable);
+            }
+        }
+
+        // Same choice as the full sweep, since the stages are solved exactly the same way
+        let (v, a) = self.best_action(trace.x_k, last);
+
+        // The first stage is already in the memo
+        if k > 0 {
+            trace.memo.set_at(len + k - 1, (v, a));
+        }
+
+        trace.path.push(trace.x_k);
+
+        match a {
+            Action::Move(u_idx) => trace.x_k += self.stencil.moves()[u_idx],
+            _ => unreachable!("every state along the path has a finite value-to-go"),
+        }
+
+        Ok(())
+    }
+
+    /// Solves the `count` stages before the one given by `next`, leaving the first of them in
+    /// `out`. `scratch` holds the stages in between.
+    fn solve_stages(
+        &self,
+        out: &mut [f32],
+        scratch: &mut [f32],
+        next: &[f32],
+        count: usize,
+    ) -> Result<(), BrachistochroneError> {
+        // Alternate between both layers, so that the last stage solved lands in `out`
+        let (mut current, mut other) = match count % 2 {
+            1 => (out, scratch),
+            _ => (scratch, out),
+        };
+
+        for i in 0..count {
+            self.progress.check()?;
+
+            match i {
+                0 => self.solve_stage(current, next),
+                _ => {
+                    self.solve_stage(other, current);
+                    std::mem::swap(&mut current, &mut other);
+                }
+            }
+
+            self.progress.advance(1);
+        }
+
+        Ok(())
+    }
+
+    /// Value-to-go of every node at the last stage: zero at the end point, infinite elsewhere
+    fn terminal_stage(&self, layer: &mut [f32]) {
+        layer.fill(f32::INFINITY);
+        layer[self.grid.node_index(self.end)] = 0.;
+    }
+
+    /// Value-to-go of every node at some stage, given that of the next one
+    fn solve_stage(&self, layer: &mut [f32], next: &[f32]) {
+        fill_with(layer, self.threads(), |i| {
+            self.best_action(self.grid.node(i), next).0
+        });
+    }
+}
+
+/// Number of stages [`Brachistochrone::follow_stages`] solves to follow `count` of them
+fn stage_solves(count: usize) -> usize {
+    match count {
+        0 | 1 => 0,
+        _ => count - count / 2 + stage_solves(count / 2) + stage_solves(count - count / 2),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector2;
+
+    use crate::{Brachistochrone, Grid, Obstacles, Physics, Stencil};
+
+    #[test]
+    fn rolling_matches_staged_solve() {
+        let (start, end) = (Vector2::new(0., 10.), Vector2::new(12., 2.));
+        let grid = Grid::fitted(start, end, 24, 0., 10.).unwrap();
+        let physics = Physics {
+            friction: 0.1,
+            ..Default::default()
+        };
+
+        let mut obstacles = Obstacles::new();
+        obstacles.block_cell(8, 6);
+
+        let solver = || {
+            Brachistochrone::new(grid, start, end, Stencil::radius(4), physics)
+                .unwrap()
+                .with_obstacles(obstacles.clone())
+        };
+
+        let (mut staged, mut rolling) = (solver(), solver());
+
+        staged.solve().unwrap();
+        rolling.solve_rolling().unwrap();
+
+        let path = |brac: &Brachistochrone| -> Vec<_> {
+            brac.path_iter(brac.start_node())
+                .unwrap()
+                .map(|(v, x)| (v.to_bits(), x))
+                .collect()
+        };
+
+        assert_eq!(path(&staged), path(&rolling));
+        assert_eq!(staged.value_field(), rolling.value_field());
+    }
+}
@@ -0,0 +1,112 @@
+use nalgebra::Vector2;
+
+/// Smooth curve through the nodes of a path, made of cubic Hermite segments parametrized by
+/// the chord length between nodes.
+///
+/// The tangents are chosen as in a monotone (Fritsch-Carlson) spline, separately for each
+/// coordinate, so the curve never overshoots a node: wherever the nodes are monotone in `x`
+/// or in `y`, so is the curve. In particular, it doesn't go above the start point.
+pub struct SmoothPath {
+    params: Vec<f32>,
+    points: Vec<Vector2<f32>>,
+    tangents: Vec<Vector2<f32>>,
+}
+
+impl SmoothPath {
+    /// Fits the curve through `points`. Repeated points are ignored, and at least two distinct
+    /// points are needed.
+    pub fn new(points: impl IntoIterator<Item = Vector2<f32>>) -> Option<SmoothPath> {
+        let mut nodes: Vec<Vector2<f32>> = Vec::new();
+
+        for p in points {
+            if nodes.last() != Some(&p) {
+                nodes.push(p);
+            }
+        }
+
+        if nodes.len() < 2 {
+            return None;
+        }
+
+        let params: Vec<f32> = std::iter::once(0.)
+            .chain(nodes.windows(2).scan(0., |s, w| {
+                *s += (w[1] - w[0]).norm();
+                Some(*s)
+            }))
+            .collect();
+
+        let secants: Vec<Vector2<f32>> = nodes
+            .windows(2)
+            .zip(params.windows(2))
+            .map(|(p, s)| (p[1] - p[0]) / (s[1] - s[0]))
+            .collect();
+
+        let mut tangents = Vec::with_capacity(nodes.len());
+
+        tangents.push(secants[0]);
+        tangents.extend(
+            secants
+                .windows(2)
+                .map(|d| d[0].zip_map(&d[1], |a, b| if a * b > 0. { (a + b) / 2. } else { 0. })),
+        );
+        tangents.push(secants[secants.len() - 1]);
+
+        // Limit the tangents so every segment stays monotone where its endpoints are
+        for (i, d) in secants.iter().enumerate() {
+            for c in 0..2 {
+                if d[c] == 0. {
+                    tangents[i][c] = 0.;
+                    tangents[i + 1][c] = 0.;
+                    continue;
+                }
+
+                let a = tangents[i][c] / d[c];
+                let b = tangents[i + 1][c] / d[c];
+                let norm = a.hypot(b);
+
+                if norm > 3. {
+                    tangents[i][c] = 3. * a / norm * d[c];
+                    tangents[i + 1][c] = 3. * b / norm * d[c];
+                }
+            }
+        }
+
+        Some(SmoothPath {
+            params,
+            points: nodes,
+            tangents,
+        })
+    }
+
+    /// Range of the curve parameter, i.e. the length of the polyline through the nodes
+    pub fn param_len(&self) -> f32 {
+        self.params[self.params.len() - 1]
+    }
+
+    /// Point at parameter `s`, clamped to `[0, param_len]`
+    pub fn point(&self, s: f32) -> Vector2<f32> {
+        let s = s.clamp(0., self.param_len());
+        let i = self
+            .params
+            .partition_point(|p| *p <= s)
+            .clamp(1, self.params.len() - 1)
+            - 1;
+
+        let h = self.params[i + 1] - self.params[i];
+        let t = (s - self.params[i]) / h;
+
+        let t2 = t * t;
+        let t3 = t2 * t;
+
+        (2. * t3 - 3. * t2 + 1.) * self.points[i]
+            + (t3 - 2. * t2 + t) * h * self.tangents[i]
+            + (-2. * t3 + 3. * t2) * self.points[i + 1]
+            + (t3 - t2) * h * self.tangents[i + 1]
+    }
+
+    /// Samples `n + 1` points evenly spaced in the curve parameter, from the first node to the
+    /// last one
+    pub fn sample(&self, n: usize) -> impl Iterator<Item = Vector2<f32>> {
+        (0..=n).map(move |i| self.point(self.param_len() * i as f32 / n as f32))
+    }
+}
@@ -0,0 +1,217 @@
+use nalgebra::Vector2;
+
+use super::{G, Physics};
+
+/// Speed at which the path can be travelled at each point, which is all the solver needs to
+/// know about the problem. Paths minimizing the travel time through a speed field are the ones
+/// followed by light (Fermat's principle), where the speed is `c / n` for a refractive index
+/// `n`.
+pub trait SpeedField: Send + Sync {
+    /// Speed at `p` (in world coordinates). Zero or NaN where the path can't go.
+    fn speed(&self, p: Vector2<f32>) -> f32;
+
+    /// Time taken to go from `a` to `b` along a straight segment, by Simpson's rule. Should be
+    /// overridden when there's an exact expression, or when the speed isn't smooth.
+    fn segment_time(&self, a: Vector2<f32>, b: Vector2<f32>) -> f32 {
+        let mid = 0.5 * (a + b);
+
+        (b - a).norm() / 6. * (1. / self.speed(a) + 4. / self.speed(mid) + 1. / self.speed(b))
+    }
+}
+
+/// Speed of a body moving along the curve under gravity, given by the work-energy theorem:
+/// the kinetic energy per unit mass is `(1 + k) v0^2 / 2 + g (y_start - y)`, minus the work done
+/// by friction, where `k` is the inertia factor of the body. Drag is ignored, since it makes
+/// the speed depend on the path, see [`super::Brachistochrone::solve_forward`].
+///
+/// Along a straight segment the normal force is `m g cos(a)`, so friction does work
+/// `friction * m g |dx|` regardless of the slope. As long as the path doesn't move backwards
+/// in `x`, the horizontal distance travelled up to `p` is always `x - x_start`, so the speed is
+/// a function of the position alone, even though it generally depends on the path taken once
+/// there's friction. With backward moves, friction losses are underestimated, which is why the
+/// solvers then carry the speed along the path instead, see
+/// [`super::Brachistochrone::solve_forward`].
+#[derive(Debug, Clone, Copy)]
+pub struct GravitySpeed {
+    pub start: Vector2<f32>,
+    pub physics: Physics,
+}
+
+impl SpeedField for GravitySpeed {
+    fn speed(&self, p: Vector2<f32>) -> f32 {
+        let potential = 2. * G * (self.start.y - p.y)
+            - 2. * G * self.physics.sliding_friction() * (p.x - self.start.x);
+
+        (self.physics.initial_speed.powi(2) + potential / (1. + self.physics.body.inertia_factor()))
+            .sqrt()
+    }
+
+    fn segment_time(&self, a: Vector2<f32>, b: Vector2<f32>) -> f32 {
+        // The acceleration is constant along the segment, so the average speed
+        // is the mean of the speeds at both ends
+        2. * (b - a).norm() / (self.speed(b) + self.speed(a))
+    }
+}
+
+/// Medium made of horizontal layers, each with its own constant speed, e.g. light going through
+/// stacked slabs of glass
+#[derive(Debug, Clone)]
+pub struct LayeredMedium {
+    base_speed: f32,
+    // Sorted by height: the speed above each boundary, up to the next one
+    layers: Vec<(f32, f32)>,
+}
+
+impl LayeredMedium {
+    /// Medium with speed `base_speed` everywhere, until layers are added
+    pub fn new(base_speed: f32) -> Self {
+        LayeredMedium {
+            base_speed,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Sets the speed to `speed` from height `y` upwards, up to the next layer
+    pub fn add_layer(&mut self, y: f32, speed: f32) -> &mut Self {
+        let i = self.layers.partition_point(|(b, _)| *b < y);
+
+        self.layers.insert(i, (y, speed));
+        self
+    }
+
+    fn boundaries(&self) -> impl Iterator<Item = f32> {
+        self.layers.iter().map(|(y, _)| *y)
+    }
+
+    /// Least-time path from `a` to `b`, which is a straight line within each layer, bending at
+    /// every boundary according to Snell's law: `sin(angle) / v` is the same in every layer.
+    /// Has a vertex wherever it crosses a boundary. `None` if the speed isn't positive
+    /// everywhere between both points.
+    pub fn snell_path(&self, a: Vector2<f32>, b: Vector2<f32>) -> Option<Vec<Vector2<f32>>> {
+        if a.y == b.y {
+            return (self.speed(a) > 0.).then(|| vec![a, b]);
+        }
+
+        let (lo, hi) = (a.y.min(b.y), a.y.max(b.y));
+
+        // Heights at which the path goes from one layer into the next, from `a` to `b`
+        let mut heights: Vec<f32> = std::iter::once(lo)
+            .chain(self.boundaries().filter(|y| *y > lo && *y < hi))
+            .chain(std::iter::once(hi))
+            .collect();
+
+        if a.y > b.y {
+            heights.reverse();
+        }
+
+        // Vertical extent and speed of each piece of the path
+        let pieces: Vec<(f64, f64)> = heights
+            .windows(2)
+            .map(|h| {
+                let speed = self.speed(Vector2::new(a.x, 0.5 * (h[0] + h[1])));
+                ((h[1] - h[0]).abs() as f64, speed as f64)
+            })
+            .collect();
+
+        if pieces.iter().any(|(_, v)| v.is_nan() || *v <= 0.) {
+            return None;
+        }
+
+        // Horizontal distance covered by each piece of the ray with parameter `p = sin(angle) / v`
+        let steps = |p: f64| {
+            pieces
+                .iter()
+                .map(move |&(h, v)| h * p * v / (1. - (p * v).powi(2)).sqrt())
+        };
+
+        // The total distance grows from 0 to infinity as `p` goes to `1 / max_speed`, so the
+        // parameter can be found by bisection
+        let dx = (b.x - a.x).abs() as f64;
+        let max_speed = pieces.iter().map(|(_, v)| *v).fold(0., f64::max);
+        let (mut lo, mut hi) = (0., 1. / max_speed);
+
+        for _ in 0..100 {
+            let mid = 0.5 * (lo + hi);
+
+            if steps(mid).sum::<f64>() < dx {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+
+        let mut points = vec![a];
+        let mut x = a.x;
+
+        for (step, y) in steps(0.5 * (lo + hi)).zip(&heights[1..]) {
+            x += (b.x - a.x).signum() * step as f32;
+            points.push(Vector2::new(x, *y));
+        }
+
+        // Get rid of the accumulated error
+        *points.last_mut()? = b;
+
+        Some(points)
+    }
+}
+
+impl SpeedField for LayeredMedium {
+    fn speed(&self, p: Vector2<f32>) -> f32 {
+        let i = self.layers.partition_point(|(y, _)| *y <= p.y);
+
+        match i {
+            0 => self.base_speed,
+            _ => self.layers[i - 1].1,
+        }
+    }
+
+    /// Exact, since the speed is constant between the points where the segment crosses a
+    /// boundary
+    fn segment_time(&self, a: Vector2<f32>, b: Vector2<f32>) -> f32 {
+        let mut ts: Vec<f32> = self
+            .boundaries()
+            .filter(|y| (a.y - y) * (b.y - y) < 0.)
+            .map(|y| (y - a.y) / (b.y - a.y))
+            .chain([0., 1.])
+            .collect();
+
+        ts.sort_by(f32::total_cmp);
+
+        let len = (b - a).norm();
+
+        ts.windows(2)
+            .map(|t| len * (t[1] - t[0]) / self.speed(a + 0.5 * (t[0] + t[1]) * (b - a)))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Brachistochrone, Grid, Physics, Stencil};
+
+    #[test]
+    fn layered_medium_matches_snell() {
+        let (start, end) = (Vector2::new(0., 10.), Vector2::new(10., 0.));
+        let grid = Grid::fitted(start, end, 40, 0., 10.).unwrap();
+
+        let mut medium = LayeredMedium::new(1.);
+        medium.add_layer(5., 2.);
+
+        let mut brac =
+            Brachistochrone::new(grid, start, end, Stencil::default(), Physics::default())
+                .unwrap()
+                .with_speed_field(medium.clone());
+
+        brac.solve_dijkstra().unwrap();
+
+        let cmp = brac.compare_to_snell(&medium).unwrap();
+        let spacing = grid.spacing.max();
+
+        // The DP path is made of grid moves, so it can only be slower, and it should stay within
+        // a cell of the exact path
+        assert!(cmp.time_error > -1e-4);
+        assert!(cmp.time_error < 1e-3 * brac.total_time().unwrap());
+        assert!(cmp.max_distance < spacing);
+    }
+}
@@ -0,0 +1,97 @@
+use nalgebra::Vector2;
+
+use super::GridPoint;
+
+/// Set of moves (actions) available at every state of the DP, as offsets in grid units.
+///
+/// The zero move is always part of the stencil: the DP has a fixed time horizon, so paths
+/// that reach the end point early have to wait there until the last stage.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stencil {
+    moves: Vec<GridPoint>,
+}
+
+impl Default for Stencil {
+    /// Every move with `dx` in `0..=8` and `dy` in `-8..=8`
+    fn default() -> Self {
+        Stencil::square(8)
+    }
+}
+
+impl Stencil {
+    /// Builds a stencil from arbitrary integer moves, adding the zero move if it's missing
+    pub fn new(moves: impl IntoIterator<Item = GridPoint>) -> Stencil {
+        let mut moves: Vec<_> = moves.into_iter().collect();
+
+        if !moves.contains(&Vector2::zeros()) {
+            moves.insert(0, Vector2::zeros());
+        }
+
+        Stencil { moves }
+    }
+
+    /// Every move with `dx` in `0..=r` and `dy` in `-r..=r`
+    pub fn square(r: i32) -> Stencil {
+        Stencil::new((0..=r).flat_map(|dx| (-r..=r).map(move |dy| Vector2::new(dx, dy))))
+    }
+
+    /// Every move going forward in `x` with length at most `r`
+    pub fn radius(r: i32) -> Stencil {
+        Stencil::new(
+            Stencil::square(r)
+                .moves
+                .into_iter()
+                .filter(|u| u.x * u.x + u.y * u.y <= r * r),
+        )
+    }
+
+    /// Drops every move that is a multiple of a shorter one in the same direction, e.g. `(2, 2)`
+    /// since it's covered by two `(1, 1)` moves. Makes the DP considerably faster, at the cost
+    /// of needing a larger time horizon to reach far away points.
+    pub fn coprime(mut self) -> Stencil {
+        self.moves.retain(|u| {
+            let (dx, dy) = (u.x.abs(), u.y.abs());
+
+            (dx == 0 && dy == 0) || gcd(dx, dy) == 1
+        });
+
+        self
+    }
+
+    /// Adds the mirror image (along the `y` axis) of every move going forward in `x`, so the
+    /// path is allowed to move backwards.
+    ///
+    /// Note that the friction model of the other solvers assumes the path never moves
+    /// backwards, see [`super::GravitySpeed`], so with friction every solver goes through
+    /// [`super::Brachistochrone::solve_forward`] once the stencil has backward moves.
+    pub fn with_backward(mut self) -> Stencil {
+        let backward: Vec<_> = self
+            .moves
+            .iter()
+            .filter(|u| u.x > 0)
+            .map(|u| Vector2::new(-u.x, u.y))
+            .filter(|u| !self.moves.contains(u))
+            .collect();
+
+        self.moves.extend(backward);
+        self
+    }
+
+    pub fn moves(&self) -> &[GridPoint] {
+        &self.moves
+    }
+
+    /// Number of moves. Never zero, since the zero move is always there.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+}
+
+fn gcd(mut a: i32, mut b: i32) -> i32 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+
+    a
+}
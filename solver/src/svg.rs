@@ -0,0 +1,305 @@
+use std::fmt::Write;
+
+use nalgebra::Vector2;
+
+use super::Brachistochrone;
+use super::cycloid::Cycloid;
+
+/// Space around the plot for the axes and their labels, in pixels
+const MARGIN_LEFT: f32 = 60.;
+const MARGIN_RIGHT: f32 = 20.;
+const MARGIN_TOP: f32 = 20.;
+const MARGIN_BOTTOM: f32 = 50.;
+
+/// Smallest size of the plot area along either axis, in pixels, so the legend fits
+const MIN_PLOT_SIZE: f32 = 150.;
+
+/// At most this many grid lines are drawn along each axis, skipping some if needed
+const MAX_GRID_LINES: usize = 50;
+
+/// What to draw in the figure made by [`Brachistochrone::to_svg`]
+#[derive(Debug, Clone, Copy)]
+pub struct SvgOptions {
+    /// Size of the plot area along the longest side of the grid, in pixels. The other side
+    /// follows from the aspect ratio of the grid.
+    pub width: f32,
+    /// Draw the lines of the grid
+    pub grid: bool,
+    /// Draw the cycloid through both endpoints, if there's one
+    pub cycloid: bool,
+    /// Draw the straight line between both endpoints
+    pub straight_line: bool,
+    /// Name of the path in the legend, which depends on where it comes from
+    pub label: &'static str,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        SvgOptions {
+            width: 600.,
+            grid: true,
+            cycloid: false,
+            straight_line: false,
+            label: "DP path",
+        }
+    }
+}
+
+/// Maps world coordinates to SVG coordinates, where `y` points down
+struct Viewport {
+    min: Vector2<f32>,
+    max: Vector2<f32>,
+    scale: f32,
+}
+
+impl Viewport {
+    fn map(&self, p: Vector2<f32>) -> (f32, f32) {
+        (
+            MARGIN_LEFT + (p.x - self.min.x) * self.scale,
+            MARGIN_TOP + (self.max.y - p.y) * self.scale,
+        )
+    }
+
+    fn polyline(&self, points: &[Vector2<f32>]) -> String {
+        let mut s = String::new();
+
+        for p in points {
+            let (x, y) = self.map(*p);
+            let _ = write!(s, "{x:.2},{y:.2} ");
+        }
+
+        s.trim_end().to_owned()
+    }
+}
+
+/// Distance between axis ticks: 1, 2 or 5 times a power of 10, giving at most about 10 ticks
+fn tick_step(range: f32) -> f32 {
+    let rough = range / 10.;
+    let magnitude = 10_f32.powf(rough.log10().floor());
+
+    [1., 2., 5., 10.]
+        .into_iter()
+        .map(|m| m * magnitude)
+        .find(|step| *step >= rough)
+        .unwrap_or(10. * magnitude)
+}
+
+/// Multiples of `step` within `[min, max]`
+fn ticks(min: f32, max: f32, step: f32) -> impl Iterator<Item = f32> {
+    let first = (min / step - 1e-3).ceil() as i64;
+    let last = (max / step + 1e-3).floor() as i64;
+
+    (first..=last).map(move |k| k as f32 * step)
+}
+
+/// Decimal places needed to print multiples of `step`
+fn decimals(step: f32) -> usize {
+    (-step.log10().floor()).max(0.) as usize
+}
+
+impl Brachistochrone {
+    /// Figure of the problem as an SVG document: the grid, the obstacles, both endpoints and
+    /// the path through `points` (in world coordinates), along with axes in metres and a legend.
+    /// `points` is usually [`Brachistochrone::world_path`], but may come from elsewhere, e.g. a
+    /// [`super::PathCache`].
+    pub fn to_svg(&self, points: &[Vector2<f32>], options: &SvgOptions) -> String {
+        let grid = self.grid;
+        let min = grid.origin;
+        let max = grid.to_world(Vector2::new(grid.nx as i32, grid.ny as i32));
+
+        // The grid has no width at all if both endpoints are above each other
+        let scale = options.width / (max - min).max();
+
+        // Narrow grids are centered in a wider plot, with room for the legend
+        let pad = (Vector2::repeat(MIN_PLOT_SIZE / scale) - (max - min)).map(|d| d.max(0.) / 2.);
+        let (min, max) = (min - pad, max + pad);
+        let view = Viewport { min, max, scale };
+
+        let (width, height) = ((max.x - min.x) * scale, (max.y - min.y) * scale);
+        let total_width = MARGIN_LEFT + width + MARGIN_RIGHT;
+        let total_height = MARGIN_TOP + height + MARGIN_BOTTOM;
+
+        let start = grid.to_world(self.start);
+        let end = grid.to_world(self.end);
+
+        let mut svg = String::new();
+
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{total_width:.0}" height="{total_height:.0}" viewBox="0 0 {total_width:.2} {total_height:.2}" font-family="sans-serif" font-size="12">"#
+        );
+        let _ = writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#);
+
+        if options.grid {
+            let _ = writeln!(svg, r##"<g stroke="#e0e0e0" stroke-width="0.5">"##);
+
+            for i in (0..=grid.nx).step_by(grid.nx.div_ceil(MAX_GRID_LINES).max(1)) {
+                let (x, y0) = view.map(grid.to_world(Vector2::new(i as i32, 0)));
+                let (_, y1) = view.map(grid.to_world(Vector2::new(i as i32, grid.ny as i32)));
+                let _ = writeln!(
+                    svg,
+                    r#"<line x1="{x:.2}" y1="{y0:.2}" x2="{x:.2}" y2="{y1:.2}"/>"#
+                );
+            }
+
+            for j in (0..=grid.ny).step_by(grid.ny.div_ceil(MAX_GRID_LINES).max(1)) {
+                let (x0, y) = view.map(grid.to_world(Vector2::new(0, j as i32)));
+                let (x1, _) = view.map(grid.to_world(Vector2::new(grid.nx as i32, j as i32)));
+                let _ = writeln!(
+                    svg,
+                    r#"<line x1="{x0:.2}" y1="{y:.2}" x2="{x1:.2}" y2="{y:.2}"/>"#
+                );
+            }
+
+            let _ = writeln!(svg, "</g>");
+        }
+
+        for polygon in self.obstacles.to_polygons(&grid) {
+            let _ = writeln!(
+                svg,
+                r##"<polygon points="{}" fill="#5a8ce6" fill-opacity="0.4" stroke="#5a8ce6"/>"##,
+                view.polyline(&polygon)
+            );
+        }
+
+        // Axes along the bottom and left sides of the plot, with ticks at round values
+        let (left, bottom) = view.map(min);
+        let (right, top) = view.map(max);
+
+        let _ = writeln!(svg, r#"<g stroke="black" stroke-width="1">"#);
+        let _ = writeln!(
+            svg,
+            r#"<line x1="{left:.2}" y1="{bottom:.2}" x2="{right:.2}" y2="{bottom:.2}"/>"#
+        );
+        let _ = writeln!(
+            svg,
+            r#"<line x1="{left:.2}" y1="{bottom:.2}" x2="{left:.2}" y2="{top:.2}"/>"#
+        );
+        let _ = writeln!(svg, "</g>");
+
+        let step = tick_step((max.x - min.x).max(max.y - min.y));
+        let mut labels = String::new();
+
+        for x in ticks(min.x, max.x, step) {
+            let (px, _) = view.map(Vector2::new(x, min.y));
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{px:.2}" y1="{bottom:.2}" x2="{px:.2}" y2="{:.2}" stroke="black"/>"#,
+                bottom + 5.
+            );
+            let _ = writeln!(
+                labels,
+                r#"<text x="{px:.2}" y="{:.2}" text-anchor="middle">{x:.decimals$}</text>"#,
+                bottom + 18.,
+                decimals = decimals(step)
+            );
+        }
+
+        for y in ticks(min.y, max.y, step) {
+            let (_, py) = view.map(Vector2::new(min.x, y));
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{left:.2}" y1="{py:.2}" x2="{:.2}" y2="{py:.2}" stroke="black"/>"#,
+                left - 5.
+            );
+            let _ = writeln!(
+                labels,
+                r#"<text x="{:.2}" y="{:.2}" text-anchor="end">{y:.decimals$}</text>"#,
+                left - 8.,
+                py + 4.,
+                decimals = decimals(step)
+            );
+        }
+
+        svg.push_str(&labels);
+
+        let _ = writeln!(
+            svg,
+            r#"<text x="{:.2}" y="{:.2}" text-anchor="middle">x [m]</text>"#,
+            (left + right) / 2.,
+            bottom + 38.
+        );
+        let _ = writeln!(
+            svg,
+            r#"<text x="{:.2}" y="{:.2}" text-anchor="middle" transform="rotate(-90 {:.2} {:.2})">y [m]</text>"#,
+            left - 42.,
+            (top + bottom) / 2.,
+            left - 42.,
+            (top + bottom) / 2.
+        );
+
+        // Curves, along with their legend entries
+        let mut legend = Vec::new();
+
+        if options.straight_line {
+            let _ = writeln!(
+                svg,
+                r##"<polyline points="{}" fill="none" stroke="#888888" stroke-width="1.5" stroke-dasharray="2 4"/>"##,
+                view.polyline(&[start, end])
+            );
+            legend.push(("#888888", "2 4", "Straight line"));
+        }
+
+        if options.cycloid
+            && let Some(cycloid) = Cycloid::new(start, end)
+        {
+            let points: Vec<_> = cycloid.sample(256).collect();
+
+            let _ = writeln!(
+                svg,
+                r##"<polyline points="{}" fill="none" stroke="#e07020" stroke-width="1.5" stroke-dasharray="8 4"/>"##,
+                view.polyline(&points)
+            );
+            legend.push(("#e07020", "8 4", "Cycloid"));
+        }
+
+        let _ = writeln!(
+            svg,
+            r##"<polyline points="{}" fill="none" stroke="#202080" stroke-width="2"/>"##,
+            view.polyline(points)
+        );
+        legend.push(("#202080", "none", options.label));
+
+        for (p, color, label) in [(start, "#20a040", "Start"), (end, "#c02020", "End")] {
+            let (x, y) = view.map(p);
+            let _ = writeln!(
+                svg,
+                r#"<circle cx="{x:.2}" cy="{y:.2}" r="4" fill="{color}"/>"#
+            );
+            let _ = writeln!(
+                svg,
+                r#"<text x="{:.2}" y="{:.2}">{label}</text>"#,
+                x + 7.,
+                y - 7.
+            );
+        }
+
+        let (lx, ly) = (right - 130., top + 10.);
+
+        let _ = writeln!(
+            svg,
+            r##"<rect x="{lx:.2}" y="{ly:.2}" width="120" height="{}" fill="white" fill-opacity="0.85" stroke="#888888"/>"##,
+            10 + 18 * legend.len()
+        );
+
+        for (i, (color, dash, label)) in legend.iter().enumerate() {
+            let y = ly + 18. + 18. * i as f32;
+
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{:.2}" y1="{y:.2}" x2="{:.2}" y2="{y:.2}" stroke="{color}" stroke-width="2" stroke-dasharray="{dash}"/>"#,
+                lx + 8.,
+                lx + 38.
+            );
+            let _ = writeln!(
+                svg,
+                r#"<text x="{:.2}" y="{:.2}">{label}</text>"#,
+                lx + 46.,
+                y + 4.
+            );
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
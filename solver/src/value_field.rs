@@ -0,0 +1,204 @@
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+use nalgebra::Vector2;
+
+use super::{Action, Brachistochrone, Grid, GridPoint, png};
+
+/// Colour map for the heatmap, from the lowest value to the highest one
+const COLORS: [[u8; 3]; 5] = [
+    [13, 8, 135],
+    [126, 3, 168],
+    [204, 71, 120],
+    [248, 149, 64],
+    [240, 249, 33],
+];
+
+/// Colour of the nodes the end point can't be reached from
+const UNREACHABLE_COLOR: [u8; 3] = [40, 40, 40];
+
+/// Least time to get to the end point from every node, along with the move to make there, as
+/// given by [`Brachistochrone::value_field`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueField {
+    pub grid: Grid,
+    /// Value-to-go of each node, laid out as in [`Grid::node_index`]. Infinite where the end
+    /// point can't be reached.
+    pub values: Vec<f32>,
+    /// Optimal move from each node, laid out as in [`Grid::node_index`]. `None` at the end point
+    /// and where it can't be reached.
+    pub policy: Vec<Option<GridPoint>>,
+}
+
+impl ValueField {
+    /// Value-to-go of node `x`, `None` if it lies outside of the grid
+    pub fn value(&self, x: GridPoint) -> Option<f32> {
+        self.grid.checked_node_index(x).map(|i| self.values[i])
+    }
+
+    /// Optimal move from node `x`, `None` if there's none or if it lies outside of the grid
+    pub fn action(&self, x: GridPoint) -> Option<GridPoint> {
+        self.grid.checked_node_index(x).and_then(|i| self.policy[i])
+    }
+
+    /// Writes one `x,y,value,dx,dy` row per node after a header, with the position in world
+    /// coordinates and the move in grid units. The move is left empty where there's none.
+    pub fn write_csv(&self, mut w: impl Write) -> io::Result<()> {
+        writeln!(w, "x,y,value,dx,dy")?;
+
+        for (i, (v, u)) in self.values.iter().zip(&self.policy).enumerate() {
+            let p = self.grid.to_world(self.grid.node(i));
+
+            match u {
+                Some(u) => writeln!(w, "{},{},{v},{},{}", p.x, p.y, u.x, u.y)?,
+                None => writeln!(w, "{},{},{v},,", p.x, p.y)?,
+            }
+        }
+
+        w.flush()
+    }
+
+    /// Writes the grid along with the values and the policy as matrices, with one row per row
+    /// of nodes from the bottom, i.e. `values[j][i]` is the value of node `(i, j)`. Infinite
+    /// values and missing moves are `null`.
+    pub fn write_json(&self, mut w: impl Write) -> io::Result<()> {
+        let grid = &self.grid;
+        let mut values = String::new();
+        let mut policy = String::new();
+
+        for j in 0..=grid.ny as i32 {
+            let sep = if j == 0 { "" } else { "," };
+
+            let row =
+                (0..=grid.nx as i32).map(|i| self.values[grid.node_index(Vector2::new(i, j))]);
+            let row: Vec<_> = row
+                .map(|v| match v.is_finite() {
+                    true => v.to_string(),
+                    false => "null".into(),
+                })
+                .collect();
+            let _ = write!(values, "{sep}\n    [{}]", row.join(", "));
+
+            let row = (0..=grid.nx as i32).map(|i| self.action(Vector2::new(i, j)));
+            let row: Vec<_> = row
+                .map(|u| match u {
+                    Some(u) => format!("[{}, {}]", u.x, u.y),
+                    None => "null".into(),
+                })
+                .collect();
+            let _ = write!(policy, "{sep}\n    [{}]", row.join(", "));
+        }
+
+        writeln!(
+            w,
+            "{{\n  \"nx\": {},\n  \"ny\": {},\n  \"origin\": [{}, {}],\n  \"spacing\": [{}, {}],\n  \"values\": [{values}\n  ],\n  \"policy\": [{policy}\n  ]\n}}",
+            grid.nx, grid.ny, grid.origin.x, grid.origin.y, grid.spacing.x, grid.spacing.y
+        )?;
+
+        w.flush()
+    }
+
+    /// Writes the values as a heatmap, with each node drawn as a square of `scale` pixels and
+    /// `y` pointing up. The colours go from dark blue for the end point to yellow for the
+    /// slowest node, and the nodes the end point can't be reached from are dark grey.
+    pub fn write_png(&self, w: impl Write, scale: u32) -> io::Result<()> {
+        let grid = &self.grid;
+        let scale = scale.max(1);
+        let (width, height) = ((grid.nx as u32 + 1) * scale, (grid.ny as u32 + 1) * scale);
+
+        let max = self
+            .values
+            .iter()
+            .copied()
+            .filter(|v| v.is_finite())
+            .fold(0., f32::max);
+
+        let mut pixels = Vec::with_capacity(3 * width as usize * height as usize);
+
+        for row in (0..height).rev() {
+            for col in 0..width {
+                let x = Vector2::new((col / scale) as i32, (row / scale) as i32);
+
+                pixels.extend_from_slice(&color(self.values[grid.node_index(x)], max));
+            }
+        }
+
+        png::write_rgb(w, width, height, &pixels)
+    }
+}
+
+/// Colour of `v` in the heatmap, for values going up to `max`
+fn color(v: f32, max: f32) -> [u8; 3] {
+    if !v.is_finite() {
+        return UNREACHABLE_COLOR;
+    }
+
+    let t = if max > 0. {
+        (v / max).clamp(0., 1.)
+    } else {
+        0.
+    };
+    let pos = t * (COLORS.len() - 1) as f32;
+    let i = (pos as usize).min(COLORS.len() - 2);
+    let f = pos - i as f32;
+
+    let (a, b) = (COLORS[i], COLORS[i + 1]);
+
+    [0, 1, 2].map(|c| (a[c] as f32 + f * (b[c] as f32 - a[c] as f32)).round() as u8)
+}
+
+impl Brachistochrone {
+    /// Least time to get to the end point from every node, along with the optimal move there,
+    /// at the first stage. That's the whole solution of the DP, of which
+    /// [`Brachistochrone::path_iter`] only follows a single path. Must be called after solving.
+    ///
+    /// Paths that don't need every stage may wait before moving on (see [`super::Stencil`]),
+    /// which says nothing about where they go, so waiting is replaced by the best move going by
+    /// the values of the first stage. After [`Brachistochrone::solve_multigrid`], only the nodes
+    /// in the corridor around the path have a value, and after
+    /// [`Brachistochrone::solve_forward`] only those along it.
+    pub fn value_field(&self) -> ValueField {
+        let len = self.grid.len();
+
+        let values: Vec<_> = (0..len)
+            .map(|i| self.memo.get((0, self.grid.node(i))).0)
+            .collect();
+
+        let policy = (0..len)
+            .map(|i| {
+                let x = self.grid.node(i);
+
+                match self.memo.get((0, x)) {
+                    (v, Action::Move(u_idx)) if v.is_finite() && x != self.end => {
+                        match self.stencil.moves()[u_idx] {
+                            u if u == GridPoint::zeros() => self.best_move(x, &values),
+                            u => Some(u),
+                        }
+                    }
+                    _ => None,
+                }
+            })
+            .collect();
+
+        ValueField {
+            grid: self.grid,
+            values,
+            policy,
+        }
+    }
+
+    /// Move from `x` minimizing the value-to-go given by `values`, other than the zero move
+    fn best_move(&self, x: GridPoint, values: &[f32]) -> Option<GridPoint> {
+        self.stencil
+            .moves()
+            .iter()
+            .enumerate()
+            .filter(|(u_idx, u)| {
+                **u != GridPoint::zeros() && self.grid.contains(x + *u) && self.allows(x, *u_idx)
+            })
+            .map(|(_, u)| (self.cost(x, u) + values[self.grid.node_index(x + u)], *u))
+            .filter(|(v, _)| v.is_finite())
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, u)| u)
+    }
+}
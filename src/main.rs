@@ -4,6 +4,9 @@
 
 use std::collections::HashMap;
 use std::f32;
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
 use nalgebra::Vector2;
 
@@ -33,23 +36,28 @@ use bevy_rapier2d::prelude::*;
 
 use serde::Deserialize;
 
-#[allow(dead_code)]
-mod brachistochrone;
-#[allow(unused_imports)]
-use brachistochrone::Brachistochrone;
+use brachistochrone_solver::cycloid::Cycloid;
+use brachistochrone_solver::{
+    Body, Brachistochrone, BrachistochroneError, CachedPath, Grid, Multigrid, Obstacles, PathCache,
+    Physics, Progress, Refine, SmoothPath, Solver, Stencil, SvgOptions, ValueField,
+};
 
 #[derive(Resource, Default, Clone)]
 struct BrachistochroneParams {
     start: Vector2<f32>,
     end: Vector2<f32>,
-    grid_resolution: u8,
+    grid_resolution: u16,
     // These values are taken from the initial window size
     viewport_width: f32,
     viewport_height: f32,
 
     // Default `Default` impl for these items should make sense
     friction: f32,
+    initial_speed: f32,
+    body: Body,
+    drag: f32,
     straight_line: bool,
+    obstacle: bool,
 }
 
 /// The main body under simulation (rolling on the Brachistochrone-like curve)
@@ -60,6 +68,14 @@ struct MainBody;
 #[derive(Component)]
 struct BrachistochronePath;
 
+/// Obstacles the path has to go around
+#[derive(Component)]
+struct Obstacle;
+
+/// Text of the button used to pick the type of body
+#[derive(Component)]
+struct BodyButtonMarker;
+
 /// Simulation time UI element
 /// Keeps track of the time instant when the simulation began
 #[derive(Component)]
@@ -69,6 +85,14 @@ enum SimulationTime {
     Frozen,
 }
 
+/// UI element showing why the Brachistochrone path couldn't be generated, if that's the case
+#[derive(Component)]
+struct ErrorMessage;
+
+/// Filled part of the bar showing how far along the solver is
+#[derive(Component)]
+struct SolveProgressBar;
+
 #[derive(Resource, Deserialize)]
 struct Localization(HashMap<String, String>);
 
@@ -88,8 +112,20 @@ const PX_PER_M: f32 = 50.;
 const MAIN_BODY_RADIUS: f32 = PX_PER_M / 2. * f32::consts::FRAC_1_SQRT_PI;
 const MAIN_BODY_DENSITY: f32 = 4.;
 
+/// Friction coefficient for rolling bodies, high enough that they (mostly) don't slip
+const ROLLING_FRICTION: f32 = 1.;
+
 const PATH_SPAWN_OFFSET: Vec2 = Vec2::new(-MAIN_BODY_RADIUS / 4., -MAIN_BODY_RADIUS);
 
+/// Points of the smoothed curve spawned for every node of the DP path
+const SMOOTH_SAMPLES_PER_NODE: usize = 8;
+
+/// Step of the sliders of both endpoints, in metres
+const POSITION_STEP: f32 = 0.5;
+
+/// Size of the exported value heatmap, in pixels along its longest side
+const HEATMAP_SIZE: u32 = 512;
+
 fn main() {
     let mut app = App::new();
 
@@ -142,11 +178,15 @@ fn main() {
         friction: 0.,
         ..Default::default()
     })
+    .insert_resource(SolveCache::load())
+    .init_resource::<KeptSweep>()
     .insert_resource(l10n)
     .insert_resource(UiTheme(create_dark_theme()))
     .add_systems(Startup, setup)
     .add_systems(Update, consume_brachistochrone_path)
-    .add_systems(Update, show_simulation_time);
+    .add_systems(Update, show_solve_progress)
+    .add_systems(Update, show_simulation_time)
+    .add_systems(Update, apply_drag);
 
     load_internal_binary_asset!(
         app,
@@ -172,6 +212,31 @@ fn setup(
     commands.spawn(simulation_time_ui());
 }
 
+/// Quadratic drag, applied as linear damping proportional to the speed of the ball
+fn apply_drag(
+    params: Res<BrachistochroneParams>,
+    mut main_body_query: Query<(&Velocity, &mut Damping), With<MainBody>>,
+) {
+    let Ok((velocity, mut damping)) = main_body_query.single_mut() else {
+        return;
+    };
+
+    damping.linear_damping = params.drag * velocity.linvel.length() / PX_PER_M;
+}
+
+fn show_solve_progress(
+    task: Option<Res<GenerateBrachistochronePath>>,
+    mut bar_query: Query<&mut Node, With<SolveProgressBar>>,
+) {
+    let Ok(mut bar) = bar_query.single_mut() else {
+        return;
+    };
+
+    let fraction = task.map_or(0., |task| task.progress.fraction());
+
+    bar.width = percent(100. * fraction);
+}
+
 fn show_simulation_time(
     params: Res<BrachistochroneParams>,
     sim_to_render_time: Single<&SimulationToRenderTime>,
@@ -256,26 +321,92 @@ fn spawn_path_segment(
     ));
 }
 
+fn spawn_obstacles(
+    commands: &mut Commands,
+    params: &BrachistochroneParams,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+) {
+    let material = materials.add(Color::srgba(0.35, 0.55, 0.9, 1.));
+
+    for polygon in demo_obstacles(params).to_polygons(&solver_grid(params)) {
+        let vertices: Vec<_> = polygon
+            .iter()
+            .map(|v| coords((*v).into(), params))
+            .collect();
+
+        let indices: Vec<[u32; 2]> = (0..vertices.len() as u32)
+            .map(|i| [i, (i + 1) % vertices.len() as u32])
+            .collect();
+
+        for [i, j] in &indices {
+            let mesh = meshes.add(Segment2d::new(vertices[*i as usize], vertices[*j as usize]));
+
+            commands.spawn((Mesh2d(mesh), MeshMaterial2d(material.clone()), Obstacle));
+        }
+
+        commands.spawn((
+            RigidBody::Fixed,
+            Collider::polyline(vertices, Some(indices)),
+            Friction::new(params.friction),
+            Obstacle,
+        ));
+    }
+}
+
+/// Velocity (in pixels per second) the ball is spawned with, along `track_direction`, since
+/// the solver assumes the body follows the track from the start
+fn initial_velocity(params: &BrachistochroneParams, track_direction: Vec2) -> Vec2 {
+    track_direction.normalize_or_zero() * params.initial_speed * PX_PER_M
+}
+
 fn spawn_main_body(
     commands: &mut Commands,
     params: &BrachistochroneParams,
     position: Vec2,
+    velocity: Vec2,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<ColorMaterial>>,
 ) {
     let mesh = meshes.add(Circle::new(MAIN_BODY_RADIUS));
     let material = materials.add(Color::srgba(0.8, 0.2, 0.15, 1.));
 
-    commands.spawn((
+    let mut entity = commands.spawn((
         Mesh2d(mesh),
         MeshMaterial2d(material),
         RigidBody::Dynamic,
         Transform::from_translation(position.extend(0.)),
         Collider::ball(MAIN_BODY_RADIUS),
-        ColliderMassProperties::Density(MAIN_BODY_DENSITY),
-        Friction::new(params.friction),
+        Velocity::linear(velocity),
+        Damping::default(),
         MainBody,
     ));
+
+    match params.body {
+        // Can't rotate, so it slides along the curve
+        Body::PointMass => {
+            entity.insert((
+                ColliderMassProperties::Density(MAIN_BODY_DENSITY),
+                LockedAxes::ROTATION_LOCKED,
+                Friction::new(params.friction),
+            ));
+        }
+        body => {
+            let mass = MAIN_BODY_DENSITY * f32::consts::PI * (MAIN_BODY_RADIUS / PX_PER_M).powi(2);
+
+            entity.insert((
+                ColliderMassProperties::MassProperties(MassProperties {
+                    local_center_of_mass: Vec2::ZERO,
+                    mass,
+                    principal_inertia: body.inertia_factor() * mass * MAIN_BODY_RADIUS.powi(2),
+                }),
+                Friction {
+                    coefficient: ROLLING_FRICTION,
+                    combine_rule: CoefficientCombineRule::Max,
+                },
+            ));
+        }
+    }
 }
 
 /// Menu on the top right corner to allow setting simulation parameters as well as starting/stopping the simulation
@@ -319,7 +450,7 @@ fn brachistochrone_ui(params: Res<BrachistochroneParams>, l10n: Res<Localization
                             max: $max,
                             value: $value
                         },
-                        (SliderStep(0.5), SliderPrecision(1))
+                        (SliderStep(POSITION_STEP), SliderPrecision(1))
                     ),
                     observe(
                         |change: On<ValueChange<f32>>,
@@ -355,7 +486,7 @@ fn brachistochrone_ui(params: Res<BrachistochroneParams>, l10n: Res<Localization
             justify_self: JustifySelf::End,
             display: Display::Grid,
             grid_template_columns: vec![GridTrack::min_content(), GridTrack::fr(1.)],
-            grid_template_rows: vec![RepeatedGridTrack::auto(13)],
+            grid_template_rows: vec![RepeatedGridTrack::auto(21)],
             ..Default::default()
         },
         TabGroup::default(),
@@ -370,16 +501,16 @@ fn brachistochrone_ui(params: Res<BrachistochroneParams>, l10n: Res<Localization
                     slider(
                         SliderProps {
                             min: 10.,
-                            max: 150.,
+                            max: 1000.,
                             value: params.grid_resolution as f32
                         },
-                        (SliderStep(5.), SliderPrecision(0))
+                        (SliderStep(10.), SliderPrecision(0))
                     ),
                     observe(
                         |change: On<ValueChange<f32>>,
                          commands: Commands,
                          mut params: ResMut<BrachistochroneParams>| {
-                            params.grid_resolution = change.value as u8;
+                            params.grid_resolution = change.value as u16;
                             slider_self_update(change, commands);
                         }
                     )
@@ -409,6 +540,75 @@ fn brachistochrone_ui(params: Res<BrachistochroneParams>, l10n: Res<Localization
                     )
                 )]
             ),
+            label!("initial_speed"),
+            (
+                // [slider]
+                Node::default(),
+                children![(
+                    slider(
+                        SliderProps {
+                            min: 0.,
+                            max: 10.,
+                            value: params.initial_speed
+                        },
+                        (SliderStep(0.5), SliderPrecision(1))
+                    ),
+                    observe(
+                        |change: On<ValueChange<f32>>,
+                         commands: Commands,
+                         mut params: ResMut<BrachistochroneParams>| {
+                            params.initial_speed = change.value;
+                            slider_self_update(change, commands);
+                        }
+                    )
+                )]
+            ),
+            label!("drag"),
+            (
+                // [slider]
+                Node::default(),
+                children![(
+                    slider(
+                        SliderProps {
+                            min: 0.,
+                            max: 0.5,
+                            value: params.drag
+                        },
+                        (SliderStep(0.01), SliderPrecision(2))
+                    ),
+                    observe(
+                        |change: On<ValueChange<f32>>,
+                         commands: Commands,
+                         mut params: ResMut<BrachistochroneParams>| {
+                            params.drag = change.value;
+                            slider_self_update(change, commands);
+                        }
+                    )
+                )]
+            ),
+            label!("body"),
+            (
+                // [button cycling through the body types]
+                Node::default(),
+                children![(
+                    button(
+                        ButtonProps::default(),
+                        (),
+                        Spawn((Text::new(body_name(params.body, &l10n)), ThemedText, BodyButtonMarker))
+                    ),
+                    observe(|_: On<Activate>,
+                             l10n: Res<Localization>,
+                             mut params: ResMut<BrachistochroneParams>,
+                             mut text_query: Query<&mut Text, With<BodyButtonMarker>>| {
+                        let i = Body::ALL.iter().position(|b| *b == params.body).unwrap_or(0);
+                        params.body = Body::ALL[(i + 1) % Body::ALL.len()];
+
+                        if let Ok(mut text) = text_query.single_mut() {
+                            text.replace_range(.., body_name(params.body, &l10n));
+                        }
+                    })
+                )]
+            ),
             spacer!(),
             label!("{} [x]", l10n.get("initial_pos")),
             position_slider!(
@@ -424,7 +624,7 @@ fn brachistochrone_ui(params: Res<BrachistochroneParams>, l10n: Res<Localization
                 15.,
                 params.start.y,
                 |change: &On<ValueChange<f32>>, mut params: ResMut<BrachistochroneParams>|
-                    (params.end.y < change.value - 2.).then(|| params.start.y = change.value)
+                    (params.end.y < change.value + max_rise(&params) - 2.).then(|| params.start.y = change.value)
             ),
             spacer!(),
             label!("{} [x]", l10n.get("final_pos")),
@@ -441,7 +641,7 @@ fn brachistochrone_ui(params: Res<BrachistochroneParams>, l10n: Res<Localization
                 15.,
                 params.end.y,
                 |change: &On<ValueChange<f32>>, mut params: ResMut<BrachistochroneParams>|
-                    (params.start.y > change.value + 2.).then(|| params.end.y = change.value)
+                    (params.start.y + max_rise(&params) > change.value + 2.).then(|| params.end.y = change.value)
             ),
             spacer!(),
             (
@@ -457,6 +657,19 @@ fn brachistochrone_ui(params: Res<BrachistochroneParams>, l10n: Res<Localization
                     })
                 )]
             ),
+            (
+                Node {
+                    grid_column: GridPlacement::start(1),
+                    ..Default::default()
+                },
+                children![(
+                    checkbox((), Spawn((Text::new(l10n.get("obstacle")), ThemedText))),
+                    observe(|change: On<ValueChange<bool>>, mut params: ResMut<BrachistochroneParams>, commands: Commands| {
+                        params.obstacle = change.value;
+                        checkbox_self_update(change, commands);
+                    })
+                )]
+            ),
             spacer!(),
             (
                 // [button "start"/"reset"]
@@ -475,9 +688,13 @@ fn brachistochrone_ui(params: Res<BrachistochroneParams>, l10n: Res<Localization
                              params: Res<BrachistochroneParams>,
                              mut commands: Commands,
                              gen_path_task: Option<ResMut<GenerateBrachistochronePath>>,
+                             cache: Res<SolveCache>,
+                             kept: Res<KeptSweep>,
                              mut marker_query: Query<(&mut Text, &mut StartButtonMarker)>,
+                             mut error_query: Query<&mut Text, (With<ErrorMessage>, Without<StartButtonMarker>)>,
                              main_body_query: Query<Entity, With<MainBody>>,
                              path_segments_query: Query<Entity, With<BrachistochronePath>>,
+                             obstacles_query: Query<Entity, With<Obstacle>>,
                              mut sim_time_query: Query<&mut SimulationTime>,
                              mut meshes: ResMut<Assets<Mesh>>,
                              mut materials: ResMut<Assets<ColorMaterial>>| {
@@ -496,16 +713,23 @@ fn brachistochrone_ui(params: Res<BrachistochroneParams>, l10n: Res<Localization
                                         let end = coords(params.end.into(), &params);
 
                                         spawn_path_segment(&mut commands, &params, start + PATH_SPAWN_OFFSET, end + PATH_SPAWN_OFFSET, &mut meshes, &mut materials);
-                                        spawn_main_body(&mut commands, &params, start, &mut meshes, &mut materials);
+                                        let velocity = initial_velocity(&params, end - start);
+
+                                        spawn_main_body(&mut commands, &params, start, velocity, &mut meshes, &mut materials);
+                                        spawn_obstacles(&mut commands, &params, &mut meshes, &mut materials);
 
                                         *sim_time = SimulationTime::Valid(Instant::now());
                                     } else {
-                                        text.replace_range(.., "...");
+                                        text.replace_range(.., l10n.get("cancel"));
                                         *marker = StartButtonMarker::Waiting;
 
+                                        if let Ok(mut error) = error_query.single_mut() {
+                                            error.clear();
+                                        }
+
                                         // The simulation time should only be set once the path has actually been
                                         // generated, i.e. in `consume_brachistochrone_path`
-                                        generate_brachistochrone_path(params, commands, gen_path_task);
+                                        generate_brachistochrone_path(params, cache, kept, commands, gen_path_task);
                                     }
                                 }
                                 StartButtonMarker::Reset => {
@@ -518,15 +742,104 @@ fn brachistochrone_ui(params: Res<BrachistochroneParams>, l10n: Res<Localization
                                         commands.entity(id).despawn();
                                     }
 
-                                    for id in path_segments_query {
+                                    for id in path_segments_query.iter().chain(&obstacles_query) {
                                         commands.entity(id).despawn();
                                     }
+
+                                    commands.remove_resource::<ExportedFigure>();
+                                    commands.remove_resource::<ExportedValueField>();
+                                }
+                                StartButtonMarker::Waiting => {
+                                    // The button goes back to Start once the task fails, in
+                                    // `consume_brachistochrone_path`
+                                    if let Some(task) = gen_path_task {
+                                        task.progress.cancel();
+                                    }
                                 }
-                                StartButtonMarker::Waiting => ()
                             }
                         }
                     })
                 )]
+            ),
+            (
+                // Progress of the solver, empty unless it's running
+                Node {
+                    grid_column: GridPlacement::span(2),
+                    height: px(4),
+                    margin: UiRect::top(px(5)),
+                    ..Default::default()
+                },
+                ThemeBackgroundColor(tokens::SLIDER_BG),
+                children![(
+                    Node {
+                        width: percent(0),
+                        height: percent(100),
+                        ..Default::default()
+                    },
+                    ThemeBackgroundColor(tokens::SLIDER_BAR),
+                    SolveProgressBar
+                )]
+            ),
+            (
+                // [button "export svg"]
+                Node {
+                    grid_column: GridPlacement::span(2),
+                    margin: UiRect::top(px(5)),
+                    ..Default::default()
+                },
+                children![(
+                    button(
+                        ButtonProps::default(),
+                        (),
+                        Spawn((Text::new(l10n.get("export_svg")), ThemedText))
+                    ),
+                    observe(|_: On<Activate>, figure: Option<Res<ExportedFigure>>| {
+                        if let Some(figure) = figure {
+                            save_file("brachistochrone.svg", "image/svg+xml", figure.0.as_bytes());
+                        }
+                    })
+                )]
+            ),
+            (
+                // [button "export value field"]
+                Node {
+                    grid_column: GridPlacement::span(2),
+                    margin: UiRect::top(px(5)),
+                    ..Default::default()
+                },
+                children![(
+                    button(
+                        ButtonProps::default(),
+                        (),
+                        Spawn((Text::new(l10n.get("export_values")), ThemedText))
+                    ),
+                    observe(|_: On<Activate>, field: Option<Res<ExportedValueField>>| {
+                        let Some(field) = field else {
+                            return;
+                        };
+
+                        let grid = &field.0.grid;
+                        let scale = HEATMAP_SIZE / (grid.nx.max(grid.ny) as u32 + 1);
+                        let mut png = Vec::new();
+
+                        if field.0.write_png(&mut png, scale.max(1)).is_ok() {
+                            save_file("brachistochrone-values.png", "image/png", &png);
+                        }
+                    })
+                )]
+            ),
+            (
+                Node {
+                    grid_column: GridPlacement::span(2),
+                    padding: UiRect::axes(px(5), px(2)),
+                    ..Default::default()
+                },
+                children![(
+                    Text::new(""),
+                    TextFont::from_font_size(14.),
+                    TextColor(Color::srgb(1., 0.4, 0.35)),
+                    ErrorMessage
+                )]
             )
         ],
     )
@@ -549,11 +862,244 @@ fn simulation_time_ui() -> impl Bundle {
     )
 }
 
+/// Grid for the solver, with `params.grid_resolution` cells along the longest side of the
+/// rectangle between both endpoints. It extends down to the ground (`y = 0`), since the optimal
+/// path may go below the end point, and up to the highest point the body can reach.
+fn solver_grid(params: &BrachistochroneParams) -> Grid {
+    let top = params.start.y + max_rise(params);
+
+    // The sliders keep both endpoints apart, and the body from going very high
+    Grid::fitted(
+        params.start,
+        params.end,
+        params.grid_resolution as usize,
+        0.,
+        top,
+    )
+    .unwrap()
+}
+
+fn physics(params: &BrachistochroneParams) -> Physics {
+    Physics {
+        friction: params.friction,
+        initial_speed: params.initial_speed,
+        body: params.body,
+        drag: params.drag,
+    }
+}
+
+/// Grid covering every end point the sliders allow for the current start point, so that a
+/// sweep from the start point can be reused when only the end point moves. Every position of
+/// the sliders is a node, and the cells are at most as large as those of `solver_grid`.
+fn sweep_grid(params: &BrachistochroneParams) -> Grid {
+    let top = params.start.y + max_rise(params);
+    let longest = (params.end - params.start).abs().max();
+
+    let cells_per_step = (params.grid_resolution as f32 * POSITION_STEP / longest)
+        .ceil()
+        .max(1.);
+    let spacing = POSITION_STEP / cells_per_step;
+
+    // Up to the right and bottom edges of the sliders of the end point
+    Grid {
+        nx: ((15. - params.start.x) / spacing).round() as usize,
+        ny: (top / spacing).ceil() as usize,
+        spacing: Vector2::repeat(spacing),
+        origin: Vector2::new(params.start.x, 0.),
+    }
+}
+
+fn max_rise(params: &BrachistochroneParams) -> f32 {
+    physics(params).max_rise()
+}
+
+fn body_name<'a>(body: Body, l10n: &'a Localization) -> &'a String {
+    match body {
+        Body::PointMass => l10n.get("body_point_mass"),
+        Body::SolidSphere => l10n.get("body_solid_sphere"),
+        Body::HollowSphere => l10n.get("body_hollow_sphere"),
+        Body::SolidDisk => l10n.get("body_solid_disk"),
+        Body::Ring => l10n.get("body_ring"),
+    }
+}
+
+fn error_message<'a>(err: &BrachistochroneError, l10n: &'a Localization) -> &'a String {
+    match err {
+        BrachistochroneError::OutOfBounds(_) => l10n.get("error_out_of_bounds"),
+        BrachistochroneError::EndAboveStart => l10n.get("error_end_above_start"),
+        BrachistochroneError::SameEndpoints => l10n.get("error_same_endpoints"),
+        BrachistochroneError::Unreachable => l10n.get("error_unreachable"),
+        BrachistochroneError::AllocationTooLarge { .. } => l10n.get("error_allocation"),
+        BrachistochroneError::Cancelled => l10n.get("error_cancelled"),
+    }
+}
+
+/// When enabled, a square obstacle sitting right on the cycloid between both endpoints,
+/// so the path has to go around it
+fn demo_obstacles(params: &BrachistochroneParams) -> Obstacles {
+    let mut obstacles = Obstacles::new();
+
+    if params.obstacle
+        && let Some(cycloid) = Cycloid::new(params.start, params.end)
+    {
+        let center = cycloid.point(cycloid.theta_end() / 2.);
+        let half = f32::max(0.15 * (params.end.x - params.start.x), 0.5) / 2.;
+
+        obstacles.add_polygon(vec![
+            center + Vector2::new(-half, -half),
+            center + Vector2::new(half, -half),
+            center + Vector2::new(half, half),
+            center + Vector2::new(-half, half),
+        ]);
+    }
+
+    obstacles
+}
+
+/// Paths solved so far, shared with the tasks generating them. On native, it's also kept in
+/// the file given by the `BRACHISTOCHRONE_CACHE` environment variable, if set, so it survives
+/// restarts.
+#[derive(Resource, Clone, Default)]
+struct SolveCache {
+    paths: Arc<Mutex<PathCache>>,
+    file: Option<PathBuf>,
+}
+
+impl SolveCache {
+    fn load() -> Self {
+        #[cfg(not(target_family = "wasm"))]
+        if let Some(file) = std::env::var_os("BRACHISTOCHRONE_CACHE").map(PathBuf::from) {
+            let paths = match File::open(&file) {
+                Ok(f) => PathCache::read_from(std::io::BufReader::new(f)).unwrap_or_else(|err| {
+                    warn!("Ignoring the solve cache at {}: {err}", file.display());
+                    PathCache::new()
+                }),
+                Err(_) => PathCache::new(),
+            };
+
+            return SolveCache {
+                paths: Arc::new(Mutex::new(paths)),
+                file: Some(file),
+            };
+        }
+
+        SolveCache::default()
+    }
+
+    /// Adds a path to the cache, saving it if there's a file to save it to
+    fn insert(&self, key: u64, path: CachedPath) {
+        let mut paths = self.paths.lock().unwrap();
+        paths.insert(key, path);
+
+        let Some(file) = &self.file else {
+            return;
+        };
+
+        // Writing the file can take a while, other tasks shouldn't have to wait for it
+        let mut bytes = Vec::new();
+        let result = paths.write_to(&mut bytes);
+        drop(paths);
+
+        if let Err(err) = result.and_then(|()| std::fs::write(file, bytes)) {
+            warn!(
+                "Failed to save the solve cache to {}: {err}",
+                file.display()
+            );
+        }
+    }
+}
+
+/// Everything the sweep of [`Brachistochrone::solve_from_start`] depends on, i.e. every
+/// parameter but the end point, which only matters through the size of the cells
+#[derive(Clone, PartialEq)]
+struct SweepParams {
+    grid: Grid,
+    start: Vector2<f32>,
+    friction: f32,
+    initial_speed: f32,
+    body: Body,
+    drag: f32,
+}
+
+impl SweepParams {
+    fn new(params: &BrachistochroneParams) -> Self {
+        SweepParams {
+            grid: sweep_grid(params),
+            start: params.start,
+            friction: params.friction,
+            initial_speed: params.initial_speed,
+            body: params.body,
+            drag: params.drag,
+        }
+    }
+}
+
+/// Solver of the last sweep from the start point, along with what it was swept with. Moving
+/// only the end point after it just takes [`Brachistochrone::set_end`].
+#[derive(Resource, Clone, Default)]
+struct KeptSweep(Arc<Mutex<Option<(SweepParams, Brachistochrone)>>>);
+
+/// SVG figure of the last solved path, see [`Brachistochrone::to_svg`]
 #[derive(Resource)]
-struct GenerateBrachistochronePath(Task<CommandQueue>);
+struct ExportedFigure(String);
+
+/// Value field of the last solve, see [`Brachistochrone::value_field`]. Not available if the
+/// path came from the cache.
+#[derive(Resource)]
+struct ExportedValueField(ValueField);
+
+/// Saves `contents` to the file `name` in the working directory
+#[cfg(not(target_family = "wasm"))]
+fn save_file(name: &str, _mime: &str, contents: &[u8]) {
+    match std::fs::write(name, contents) {
+        Ok(()) => info!("Saved {name}"),
+        Err(err) => warn!("Failed to save {name}: {err}"),
+    }
+}
+
+/// Offers `contents` as a download named `name`, through a temporary object URL
+#[cfg(target_family = "wasm")]
+fn save_file(name: &str, mime: &str, contents: &[u8]) {
+    use web_sys::wasm_bindgen::{JsCast, JsValue};
+
+    let download = || -> Result<(), JsValue> {
+        let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(contents).into());
+        let options = web_sys::BlobPropertyBag::new();
+        options.set_type(mime);
+
+        let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, &options)?;
+        let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+
+        let document = web_sys::window()
+            .and_then(|w| w.document())
+            .ok_or("no document")?;
+        let anchor: web_sys::HtmlAnchorElement = document.create_element("a")?.dyn_into()?;
+
+        anchor.set_href(&url);
+        anchor.set_download(name);
+        anchor.click();
+
+        web_sys::Url::revoke_object_url(&url)
+    };
+
+    if let Err(err) = download() {
+        warn!("Failed to save {name}: {err:?}");
+    }
+}
+
+/// Task solving the path, which results in the commands spawning it along with the direction
+/// of its first segment. Its progress is shown by `show_solve_progress`, and it can be cancelled
+/// through the Start button.
+#[derive(Resource)]
+struct GenerateBrachistochronePath {
+    task: Task<Result<(CommandQueue, Vec2), BrachistochroneError>>,
+    progress: Progress,
+}
 
 fn generate_brachistochrone_path(
     params: Res<BrachistochroneParams>,
+    cache: Res<SolveCache>,
+    kept: Res<KeptSweep>,
     mut commands: Commands,
     task: Option<ResMut<GenerateBrachistochronePath>>,
 ) {
@@ -562,60 +1108,218 @@ fn generate_brachistochrone_path(
     }
 
     let params = params.clone();
+    let cache = cache.clone();
+    let kept = kept.clone();
     let pool = AsyncComputeTaskPool::get();
 
-    commands.insert_resource(GenerateBrachistochronePath(pool.spawn(async move {
+    let progress = Progress::new();
+    let task_progress = progress.clone();
+
+    let task = pool.spawn(async move {
         let mut command_queue = CommandQueue::default();
 
-        let max = f32::max(params.start.y, params.end.x);
-        let mu = max / params.grid_resolution as f32;
+        // The obstacles move along with the end point, so without them the whole sweep from the
+        // start point can be kept. Past the multigrid threshold, it would take too long.
+        let sweep = (!params.obstacle)
+            .then(|| SweepParams::new(&params))
+            .filter(|sweep| sweep.grid.nx.max(sweep.grid.ny) <= 150);
+
+        let reused = sweep
+            .as_ref()
+            .and_then(|sweep| match kept.0.lock().unwrap().take() {
+                Some((swept, brac)) if swept == *sweep => Some(brac),
+                _ => None,
+            });
+
+        // Whether `brac` holds a sweep from the start point, and so is worth keeping
+        let mut swept = reused.is_some();
+
+        let solver = match (&sweep, params.grid_resolution) {
+            (Some(_), _) => Solver::FromStart,
+            // The staged DP gets too slow past this point
+            (None, 151..) => Solver::Multigrid(Multigrid::default()),
+            // Same path as `solve`, with about `log2(time_horizon)` layers of values instead of
+            // all of them, since memory is scarce on wasm
+            (None, _) => Solver::Rolling,
+        };
+
+        let mut brac = match reused {
+            Some(brac) => {
+                // In case `set_end` has to solve again
+                let mut brac = brac.with_progress(task_progress);
+
+                brac.set_end(params.end)?;
+                brac
+            }
+            None => Brachistochrone::new(
+                match &sweep {
+                    Some(sweep) => sweep.grid,
+                    None => solver_grid(&params),
+                },
+                params.start,
+                params.end,
+                Stencil::default(),
+                physics(&params),
+            )?
+            .with_obstacles(demo_obstacles(&params))
+            .with_progress(task_progress),
+        };
+
+        if brac.snap_error() > 1e-4 {
+            warn!(
+                "Endpoints are off the grid, snapped by up to {:.4} m",
+                brac.snap_error()
+            );
+        }
+
+        // The refined path is the one stored
+        let refine = Refine::default();
+        let key = brac
+            .cache_key(solver, Some(refine))
+            .expect("no speed field is used");
+        let cached: Option<CachedPath> = cache.paths.lock().unwrap().get(key).cloned();
+
+        // The value field is only known when actually solving
+        let (path, values) = match cached {
+            Some(cached) => (cached.points, None),
+            None => {
+                // `set_end` already found the path
+                if !swept {
+                    brac.solve_with(solver)?;
+                    swept = solver == Solver::FromStart;
+                }
+
+                if params.friction == 0.
+                    && params.initial_speed == 0.
+                    && params.drag == 0.
+                    && let Some(cmp) = brac.compare_to_cycloid()
+                {
+                    info!(
+                        "DP vs. cycloid: time error = {:.4} s, max distance = {:.4} m",
+                        cmp.time_error, cmp.max_distance
+                    );
+                }
+
+                // The grid only gets close to the optimum, moving the nodes off it gets the rest
+                let solved = match brac.refine(refine) {
+                    Some(refined) => {
+                        info!(
+                            "Refined path: {:.4} s faster than the DP path after {} iterations",
+                            refined.improvement(),
+                            refined.iterations
+                        );
+
+                        if params.friction == 0.
+                            && params.initial_speed == 0.
+                            && params.drag == 0.
+                            && let Some(cmp) = brac.compare_refined_to_cycloid(&refined)
+                        {
+                            info!(
+                                "Refined vs. cycloid: time error = {:.4} s, max distance = {:.4} m",
+                                cmp.time_error, cmp.max_distance
+                            );
+                        }
+
+                        CachedPath {
+                            time: refined.time,
+                            points: refined.points,
+                        }
+                    }
+                    None => brac.cached_path(),
+                };
+
+                cache.insert(key, solved.clone());
+
+                (solved.points, Some(brac.value_field()))
+            }
+        };
+
+        // Only fails if the path is a single point
+        let samples: Vec<_> = match SmoothPath::new(path.iter().copied()) {
+            Some(smooth) => smooth
+                .sample(path.len() * SMOOTH_SAMPLES_PER_NODE)
+                .collect(),
+            None => path.clone(),
+        };
 
-        let mut brac = Brachistochrone::new(
-            params.grid_resolution as usize,
-            mu,
-            (1. / mu) * params.start,
-            (1. / mu) * params.end,
+        info!(
+            "Descent time: {:.4} s along the path, {:.4} s along the smoothed curve",
+            brac.time_along(&path),
+            brac.time_along(&samples)
         );
 
-        brac.solve();
-
-        brac.path_iter((1. / mu) * params.start)
-            .map_windows(|[(_, start), (_, end)]| {
-                let start = coords(Vec2::from(mu * start), &params);
-                let end = coords(Vec2::from(mu * end), &params);
-
-                (start, end)
-            })
-            .for_each(|(start, end)| {
-                command_queue.push(move |world: &mut World| {
-                    let mut meshes = world.resource_mut::<Assets<Mesh>>();
-                    let mesh = meshes.add(Segment2d::new(start, end));
-
-                    let mut materials = world.resource_mut::<Assets<ColorMaterial>>();
-                    let material = materials.add(Color::srgba(1., 1., 1., 1.));
-
-                    world.spawn((
-                        Mesh2d(mesh),
-                        MeshMaterial2d(material),
-                        RigidBody::Fixed,
-                        Collider::segment(start, end),
-                        BrachistochronePath,
-                    ));
-                })
-            });
+        let vertices: Vec<_> = samples
+            .iter()
+            .map(|p| coords((*p).into(), &params))
+            .collect();
+        let track_direction = vertices.get(1).map_or(Vec2::ZERO, |v| v - vertices[0]);
+        let friction = params.friction;
+
+        let figure = brac.to_svg(
+            &path,
+            &SvgOptions {
+                cycloid: true,
+                straight_line: true,
+                // Usually the refined path, but the DP one if it couldn't be refined, even when
+                // it comes from the cache
+                label: "Path",
+                ..Default::default()
+            },
+        );
 
-        command_queue
-    })));
+        command_queue.push(move |world: &mut World| {
+            world.insert_resource(ExportedFigure(figure));
+
+            if let Some(values) = values {
+                world.insert_resource(ExportedValueField(values));
+            }
+
+            let mut materials = world.resource_mut::<Assets<ColorMaterial>>();
+            let material = materials.add(Color::srgba(1., 1., 1., 1.));
+
+            for w in vertices.windows(2) {
+                let mut meshes = world.resource_mut::<Assets<Mesh>>();
+                let mesh = meshes.add(Segment2d::new(w[0], w[1]));
+
+                world.spawn((
+                    Mesh2d(mesh),
+                    MeshMaterial2d(material.clone()),
+                    BrachistochronePath,
+                ));
+            }
+
+            // A single collider for the whole curve, so the ball doesn't bump into the joints
+            // between segments
+            world.spawn((
+                RigidBody::Fixed,
+                Collider::polyline(vertices, None),
+                Friction::new(friction),
+                BrachistochronePath,
+            ));
+        });
+
+        if let Some(sweep) = sweep
+            && swept
+        {
+            *kept.0.lock().unwrap() = Some((sweep, brac));
+        }
+
+        Ok::<_, BrachistochroneError>((command_queue, track_direction))
+    });
+
+    commands.insert_resource(GenerateBrachistochronePath { task, progress });
 }
 
 /// Once the Brachistochrone path has been generated, consume it, spawn the ball
-/// (main simulation body), and change the Start button state to Reset.
+/// (main simulation body), and change the Start button state to Reset. If it
+/// couldn't be generated, show why and go back to the Start state.
 fn consume_brachistochrone_path(
     l10n: Res<Localization>,
     params: Res<BrachistochroneParams>,
     mut commands: Commands,
     task: Option<ResMut<GenerateBrachistochronePath>>,
     mut marker_query: Query<(&mut Text, &mut StartButtonMarker)>,
+    mut error_query: Query<&mut Text, (With<ErrorMessage>, Without<StartButtonMarker>)>,
     mut sim_time_query: Query<&mut SimulationTime>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
@@ -624,17 +1328,45 @@ fn consume_brachistochrone_path(
         return;
     };
 
-    let Some(mut command_queue) = check_ready(&mut task.0) else {
+    let Some(result) = check_ready(&mut task.task) else {
         return;
     };
 
     commands.remove_resource::<GenerateBrachistochronePath>();
 
+    let (mut command_queue, track_direction) = match result {
+        Ok(result) => result,
+        Err(err) => {
+            warn!("Failed to generate the Brachistochrone path: {err}");
+
+            if let Ok(mut error) = error_query.single_mut() {
+                error.0 = error_message(&err, &l10n).clone();
+            }
+
+            if let Ok((mut text, mut marker)) = marker_query.single_mut() {
+                text.replace_range(.., l10n.get("start"));
+                *marker = StartButtonMarker::Start;
+            }
+
+            return;
+        }
+    };
+
     // Move the ball up and to the right a bit, otherwise it would spawn in the middle of the Brachistochrone
     // path, leading to it clipping up or down ("falling through") unpredictably or getting stuck
     let start = coords(params.start.into(), &params) + Vec2::new(MAIN_BODY_RADIUS, 0.);
 
-    spawn_main_body(&mut commands, &params, start, &mut meshes, &mut materials);
+    let velocity = initial_velocity(&params, track_direction);
+
+    spawn_main_body(
+        &mut commands,
+        &params,
+        start,
+        velocity,
+        &mut meshes,
+        &mut materials,
+    );
+    spawn_obstacles(&mut commands, &params, &mut meshes, &mut materials);
 
     commands.append(&mut command_queue);
 